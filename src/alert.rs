@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sync::{now_millis, Versioned, VersionedMap};
+
+/// Version of the alert messages exchanged between instances ([`AlertSync`] and [`AlertClear`]),
+/// bump it on every incompatible change so instances running different versions of the plugin
+/// ignore each other instead of misreading the payload.
+pub const ALERT_SYNC_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct TabAlert {
+    pub id: String,
+    pub success: bool,
+    /// Milliseconds since the unix epoch.
+    pub raised_at: u64,
+    /// How long the command that raised the alert took, in milliseconds.
+    #[serde(default)]
+    pub duration: Option<u64>,
+    // blinking state is local to each instance
+    #[serde(skip)]
+    pub alternate_color: bool,
+}
+
+impl TabAlert {
+    pub fn new(pane_id: u32, success: bool, duration: Option<u64>) -> Self {
+        let raised_at = now_millis();
+        TabAlert {
+            id: format!("{}-{}", pane_id, raised_at),
+            success,
+            raised_at,
+            duration,
+            alternate_color: true,
+        }
+    }
+}
+
+impl Versioned for TabAlert {
+    fn updated_at(&self) -> u64 {
+        self.raised_at
+    }
+
+    // the id breaks ties between alerts raised in the same millisecond
+    fn is_newer_than(&self, other: &Self) -> bool {
+        self.id != other.id && (self.raised_at, &self.id) > (other.raised_at, &other.id)
+    }
+}
+
+/// Alerts raised on each tab (by tab index), cleared tabs reject alerts raised before they were
+/// cleared so stale alerts broadcasted by other instances aren't brought back after being
+/// acknowledged.
+#[derive(Debug, Default)]
+pub struct TabAlerts {
+    alerts: VersionedMap<usize, TabAlert>,
+}
+
+impl TabAlerts {
+    pub fn is_empty(&self) -> bool {
+        self.alerts.is_empty()
+    }
+
+    pub fn get(&self, tab_idx: &usize) -> Option<&TabAlert> {
+        self.alerts.get(tab_idx)
+    }
+
+    pub fn raise(&mut self, tab_idx: usize, alert: TabAlert) {
+        self.alerts.insert(tab_idx, alert);
+    }
+
+    pub fn toggle_blink(&mut self) {
+        for tab_alert in self.alerts.values_mut() {
+            tab_alert.alternate_color = !tab_alert.alternate_color;
+        }
+    }
+
+    /// Clears the alert of a tab and records a tombstone for it.
+    ///
+    /// Returns the clear message to send to other instances if there was an alert to clear.
+    pub fn clear(&mut self, tab_idx: usize, cleared_at: u64) -> Option<AlertClear> {
+        self.alerts.prune_tombstones(cleared_at);
+        self.alerts.get(&tab_idx)?;
+        self.alerts.remove(&tab_idx, cleared_at);
+        Some(AlertClear {
+            version: ALERT_SYNC_VERSION,
+            sent_at: cleared_at,
+            tab_idx,
+            cleared_at,
+        })
+    }
+
+    pub fn sync_message(&self) -> AlertSync {
+        AlertSync {
+            version: ALERT_SYNC_VERSION,
+            sent_at: now_millis(),
+            alerts: self.alerts.entries().clone(),
+            tombstones: self.alerts.tombstones().clone(),
+        }
+    }
+
+    /// Merges the state broadcasted by another instance, keeping the most recent alert of each
+    /// tab and dropping the ones raised before the tab was cleared. Alerts for the active tab are
+    /// ignored as they would be cleared right away.
+    ///
+    /// Returns `true` if any alert changed.
+    pub fn apply_sync(&mut self, sync: AlertSync, active_tab_index: usize) -> bool {
+        let alerts = sync
+            .alerts
+            .into_iter()
+            .filter(|(tab_idx, _)| *tab_idx != active_tab_index)
+            .map(|(tab_idx, alert)| {
+                let alert = TabAlert {
+                    alternate_color: true,
+                    ..alert
+                };
+                (tab_idx, alert)
+            });
+        let changed = self.alerts.apply_sync(alerts, sync.tombstones);
+        self.alerts.prune_tombstones(sync.sent_at);
+        changed
+    }
+
+    /// Applies a clear sent by another instance.
+    ///
+    /// Returns `true` if an alert was removed.
+    pub fn apply_clear(&mut self, tab_idx: usize, cleared_at: u64) -> bool {
+        self.alerts.apply_tombstone(tab_idx, cleared_at)
+    }
+}
+
+/// Payload of the `zj-status-bar:plugin:tab_alert:broadcast` message.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AlertSync {
+    pub version: u32,
+    /// Milliseconds since the unix epoch.
+    pub sent_at: u64,
+    pub alerts: BTreeMap<usize, TabAlert>,
+    pub tombstones: BTreeMap<usize, u64>,
+}
+
+/// Payload of the `zj-status-bar:plugin:tab_alert:clear` message, sent when an alert is
+/// acknowledged by focusing its tab.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AlertClear {
+    pub version: u32,
+    /// Milliseconds since the unix epoch.
+    pub sent_at: u64,
+    pub tab_idx: usize,
+    /// Milliseconds since the unix epoch.
+    pub cleared_at: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // alerts for the active tab are ignored, the tests raise them on another one
+    const ACTIVE_TAB: usize = 0;
+    const TAB: usize = 1;
+
+    fn alert(raised_at: u64) -> TabAlert {
+        TabAlert {
+            id: format!("1-{}", raised_at),
+            success: false,
+            raised_at,
+            duration: None,
+            alternate_color: true,
+        }
+    }
+
+    #[test]
+    fn clear_converges_across_instances() {
+        let (mut first, mut second) = (TabAlerts::default(), TabAlerts::default());
+        first.raise(TAB, alert(now_millis()));
+        assert!(second.apply_sync(first.sync_message(), ACTIVE_TAB));
+        assert!(second.get(&TAB).is_some());
+
+        let clear = second.clear(TAB, now_millis()).unwrap();
+        assert!(first.apply_clear(clear.tab_idx, clear.cleared_at));
+        assert!(first.is_empty());
+        assert!(second.is_empty());
+    }
+
+    #[test]
+    fn stale_broadcast_does_not_bring_back_cleared_alert() {
+        let (mut first, mut second) = (TabAlerts::default(), TabAlerts::default());
+        let raised_at = now_millis();
+        first.raise(TAB, alert(raised_at));
+        second.apply_sync(first.sync_message(), ACTIVE_TAB);
+        // broadcasted by the second instance before it got the clear
+        let stale = second.sync_message();
+
+        let clear = first.clear(TAB, raised_at + 1).unwrap();
+        second.apply_clear(clear.tab_idx, clear.cleared_at);
+        assert!(!first.apply_sync(stale, ACTIVE_TAB));
+        assert!(first.is_empty());
+
+        // alerts raised after the clear still go through
+        second.raise(TAB, alert(raised_at + 2));
+        assert!(first.apply_sync(second.sync_message(), ACTIVE_TAB));
+        assert_eq!(first.get(&TAB).unwrap().raised_at, raised_at + 2);
+    }
+}
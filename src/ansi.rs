@@ -0,0 +1,111 @@
+/// Piece of a string printed to the terminal, see [`tokenize`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Token<'a> {
+    /// Text without escape sequences, it can still contain other control characters.
+    Text(&'a str),
+    /// `ESC [ params terminator` sequence, SGR ones (colors and text attributes) end with `m`.
+    Csi {
+        sequence: &'a str,
+        params: &'a str,
+        terminator: char,
+    },
+    /// Any other escape sequence (OSC, character set designation, ...), or an unterminated one.
+    Escape(&'a str),
+}
+
+impl<'a> Token<'a> {
+    /// The token as it appears in the string.
+    pub fn as_str(&self) -> &'a str {
+        match self {
+            Token::Text(text) => text,
+            Token::Csi { sequence, .. } => sequence,
+            Token::Escape(sequence) => sequence,
+        }
+    }
+}
+
+/// Splits `text` into runs of text and escape sequences.
+pub fn tokenize(text: &str) -> Tokens<'_> {
+    Tokens { rest: text }
+}
+
+#[derive(Debug)]
+pub struct Tokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let Some(body) = self.rest.strip_prefix('\u{1b}') else {
+            let end = self.rest.find('\u{1b}').unwrap_or(self.rest.len());
+            let (text, rest) = self.rest.split_at(end);
+            self.rest = rest;
+            return Some(Token::Text(text));
+        };
+        let body_len = match body.chars().next() {
+            // CSI sequences end with a character in the `@`..=`~` range
+            Some('[') => body[1..]
+                .find(|c| ('@'..='~').contains(&c))
+                .map_or(body.len(), |end| end + 2),
+            // OSC sequences (e.g. window titles) end with BEL or ST (`ESC \`)
+            Some(']') => match body.find(['\u{7}', '\u{1b}']) {
+                Some(end) if body[end..].starts_with("\u{1b}\\") => end + 2,
+                Some(end) => end + 1,
+                None => body.len(),
+            },
+            // character set designations (e.g. `ESC ( B`)
+            Some(c @ ('(' | ')')) => {
+                c.len_utf8() + body[1..].chars().next().map_or(0, char::len_utf8)
+            }
+            // the rest are two characters long
+            Some(c) => c.len_utf8(),
+            None => 0,
+        };
+        let (sequence, rest) = self.rest.split_at(1 + body_len);
+        self.rest = rest;
+        let token = match sequence.strip_prefix("\u{1b}[").and_then(|csi| {
+            let terminator = csi.chars().last().filter(|c| ('@'..='~').contains(c))?;
+            Some((&csi[..csi.len() - 1], terminator))
+        }) {
+            Some((params, terminator)) => Token::Csi {
+                sequence,
+                params,
+                terminator,
+            },
+            None => Token::Escape(sequence),
+        };
+        Some(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_text_and_escape_sequences() {
+        let tokens: Vec<Token> =
+            tokenize("a\u{1b}[1;31mb\u{1b}]0;title\u{7}\u{1b}(Bc\u{1b}[").collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::Text("a"),
+                Token::Csi {
+                    sequence: "\u{1b}[1;31m",
+                    params: "1;31",
+                    terminator: 'm',
+                },
+                Token::Text("b"),
+                Token::Escape("\u{1b}]0;title\u{7}"),
+                Token::Escape("\u{1b}(B"),
+                Token::Text("c"),
+                Token::Escape("\u{1b}["),
+            ]
+        );
+    }
+}
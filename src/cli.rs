@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Outcome of a successful `zj-status-bar:cli:*` pipe command.
+#[derive(Debug, Default)]
+pub struct CliReply {
+    pub message: String,
+    pub should_render: bool,
+}
+
+impl CliReply {
+    pub fn new(message: impl Into<String>) -> Self {
+        CliReply {
+            message: message.into(),
+            should_render: false,
+        }
+    }
+
+    pub fn render(mut self) -> Self {
+        self.should_render = true;
+        self
+    }
+}
+
+pub type CliResult = Result<CliReply, String>;
+
+/// Parses an optional pipe argument, `kind` describes the expected type in the error message
+/// (e.g. "an integer").
+pub fn optional_arg<T: FromStr>(
+    args: &BTreeMap<String, String>,
+    name: &str,
+    kind: &str,
+) -> Result<Option<T>, String> {
+    args.get(name)
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| format!("{} must be {}", name, kind))
+        })
+        .transpose()
+}
+
+pub fn required_arg<T: FromStr>(
+    args: &BTreeMap<String, String>,
+    name: &str,
+    kind: &str,
+) -> Result<T, String> {
+    optional_arg(args, name, kind)?.ok_or_else(|| format!("missing {} argument", name))
+}
+
+/// Formats the line written back to the `zellij pipe` command.
+pub fn reply_line(result: &CliResult) -> String {
+    match result {
+        Ok(reply) => format!("ok: {}\n", reply.message),
+        Err(err) => format!("error: {}\n", err),
+    }
+}
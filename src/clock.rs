@@ -0,0 +1,200 @@
+// Minimal `strftime` implementation for the clock segment. Plugins run in a WASI sandbox without
+// access to the local timezone database, so times are computed from UTC plus a fixed offset.
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Parses UTC offsets like `+02:00`, `-0530` or `+2` into seconds.
+pub fn parse_utc_offset(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (sign, value) = match value.strip_prefix('-') {
+        Some(value) => (-1, value),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    // only digits, `parse` would also take signs and the split must fall on a char boundary
+    if !value.chars().all(|c| c.is_ascii_digit() || c == ':') {
+        return None;
+    }
+    let (hours, minutes) = match value.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        None if value.len() == 4 => value.split_at(2),
+        None => (value, "0"),
+    };
+    let hours: i64 = hours.parse().ok()?;
+    let minutes: i64 = minutes.parse().ok()?;
+    if hours > 14 || minutes > 59 {
+        return None;
+    }
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+// days since 1970-01-01 -> (year, month [1, 12], day [1, 31])
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn day_of_year(year: i64, month: u32, day: u32) -> u32 {
+    const DAYS_BEFORE_MONTH: [u32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+    let leap_day = u32::from(month > 2 && is_leap_year(year));
+    DAYS_BEFORE_MONTH[month as usize - 1] + day + leap_day
+}
+
+/// Formats a unix timestamp (in seconds) shifted by `utc_offset` seconds.
+///
+/// Supports `%Y %y %m %d %e %j %H %I %M %S %p %a %A %b %B %z %%`, other sequences are kept as-is.
+pub fn format_time(format: &str, unix_secs: i64, utc_offset: i64) -> String {
+    let local_secs = unix_secs + utc_offset;
+    let days = local_secs.div_euclid(86_400);
+    let secs_of_day = local_secs.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    let (hour, minute, second) = (
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+    );
+    // 1970-01-01 was a thursday
+    let weekday = WEEKDAYS[(days + 4).rem_euclid(7) as usize];
+    let month_name = MONTHS[month as usize - 1];
+
+    let mut output = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => output.push_str(&year.to_string()),
+            Some('y') => output.push_str(&format!("{:02}", year.rem_euclid(100))),
+            Some('m') => output.push_str(&format!("{:02}", month)),
+            Some('d') => output.push_str(&format!("{:02}", day)),
+            Some('e') => output.push_str(&format!("{:>2}", day)),
+            Some('j') => output.push_str(&format!("{:03}", day_of_year(year, month, day))),
+            Some('H') => output.push_str(&format!("{:02}", hour)),
+            Some('I') => output.push_str(&format!("{:02}", (hour + 11) % 12 + 1)),
+            Some('M') => output.push_str(&format!("{:02}", minute)),
+            Some('S') => output.push_str(&format!("{:02}", second)),
+            Some('p') => output.push_str(if hour < 12 { "AM" } else { "PM" }),
+            Some('a') => output.push_str(&weekday[..3]),
+            Some('A') => output.push_str(weekday),
+            Some('b') => output.push_str(&month_name[..3]),
+            Some('B') => output.push_str(month_name),
+            Some('z') => output.push_str(&format!(
+                "{}{:02}{:02}",
+                if utc_offset < 0 { '-' } else { '+' },
+                utc_offset.abs() / 3600,
+                utc_offset.abs() % 3600 / 60
+            )),
+            Some('%') => output.push('%'),
+            Some(other) => {
+                output.push('%');
+                output.push(other);
+            }
+            None => output.push('%'),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_utc_offsets() {
+        assert_eq!(parse_utc_offset("+02:00"), Some(7200));
+        assert_eq!(parse_utc_offset("-0530"), Some(-19_800));
+        assert_eq!(parse_utc_offset("2"), Some(7200));
+    }
+
+    #[test]
+    fn rejects_invalid_utc_offsets() {
+        assert_eq!(parse_utc_offset("€1"), None);
+        assert_eq!(parse_utc_offset("1€"), None);
+        assert_eq!(parse_utc_offset("+-5"), None);
+        assert_eq!(parse_utc_offset("+15"), None);
+    }
+
+    #[test]
+    fn converts_days_to_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+
+    #[test]
+    fn formats_fixed_timestamps() {
+        // 2024-02-29 00:00:00 UTC
+        let leap_day = 1_709_164_800;
+        assert_eq!(
+            format_time("%Y-%m-%d %a %b %j", leap_day, 0),
+            "2024-02-29 Thu Feb 060"
+        );
+        // 2024-12-31 00:00:00 UTC
+        let new_years_eve = 1_735_603_200;
+        assert_eq!(
+            format_time("%A %e %B %j %I%p %H", new_years_eve, 0),
+            "Tuesday 31 December 366 12AM 00"
+        );
+        assert_eq!(
+            format_time("%y-%m-%d %H:%M:%S %z", new_years_eve, -19_800),
+            "24-12-30 18:30:00 -0530"
+        );
+        assert_eq!(
+            format_time("%I %p %z", new_years_eve, 43_200),
+            "12 PM +1200"
+        );
+    }
+
+    #[test]
+    fn formats_times_before_the_epoch() {
+        assert_eq!(
+            format_time("%Y-%m-%d %H:%M %a %j %z", 0, -3600),
+            "1969-12-31 23:00 Wed 365 -0100"
+        );
+        assert_eq!(format_time("%H:%M:%S", -1, 0), "23:59:59");
+    }
+
+    #[test]
+    fn keeps_literal_percent_signs() {
+        assert_eq!(format_time("100%% %H", 0, 0), "100% 00");
+        assert_eq!(format_time("%q %", 0, 0), "%q %");
+    }
+}
@@ -0,0 +1,327 @@
+use crate::ansi::{tokenize, Token};
+
+/// Colors the terminal supports, parsed from `truecolor`, `256`, `16` or `none`. Colors the
+/// terminal can't show are replaced with the nearest one it can.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ColorDepth {
+    #[default]
+    TrueColor,
+    EightBit,
+    Ansi,
+    None,
+}
+
+impl ColorDepth {
+    pub fn parse(value: &str) -> Self {
+        match value.trim() {
+            "256" | "8bit" => ColorDepth::EightBit,
+            "16" | "ansi" => ColorDepth::Ansi,
+            "none" | "0" => ColorDepth::None,
+            _ => ColorDepth::TrueColor,
+        }
+    }
+}
+
+// xterm defaults of the 16 ANSI colors
+const ANSI_COLORS: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+// channel values of the 6x6x6 color cube (colors 16 to 231)
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+// A color set by an SGR sequence.
+#[derive(Debug, Clone, Copy)]
+enum SgrColor {
+    Ansi(u8),
+    Fixed(u8),
+    Rgb(u8, u8, u8),
+}
+
+// Which color an SGR color parameter sets, the value is the code of its 256/truecolor form.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Layer {
+    Foreground = 38,
+    Background = 48,
+    Underline = 58,
+}
+
+/// Rewrites the colors of the SGR escape sequences of `output` to fit in `depth`, other escape
+/// sequences and the text are left as is.
+pub fn downgrade_colors(output: &str, depth: ColorDepth) -> String {
+    if depth == ColorDepth::TrueColor {
+        return output.to_string();
+    }
+    let mut downgraded = String::with_capacity(output.len());
+    for token in tokenize(output) {
+        match token {
+            // an empty SGR sequence is a reset
+            Token::Csi {
+                params,
+                terminator: 'm',
+                ..
+            } if !params.is_empty() => {
+                let params = downgrade_sgr_params(params, depth);
+                // nothing left when all it did was setting colors
+                if !params.is_empty() {
+                    downgraded.push_str(&format!("\u{1b}[{}m", params));
+                }
+            }
+            token => downgraded.push_str(token.as_str()),
+        }
+    }
+    downgraded
+}
+
+fn downgrade_sgr_params(params: &str, depth: ColorDepth) -> String {
+    let mut params = params.split(';');
+    let mut downgraded: Vec<String> = vec![];
+    while let Some(param) = params.next() {
+        let color = match param {
+            "38" | "48" | "58" => {
+                let layer = layer(param);
+                let color = match params.next() {
+                    Some("5") => params
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .map(SgrColor::Fixed),
+                    Some("2") => {
+                        let mut channel = || params.next().and_then(|c| c.parse().ok());
+                        match (channel(), channel(), channel()) {
+                            (Some(r), Some(g), Some(b)) => Some(SgrColor::Rgb(r, g, b)),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                };
+                // malformed, dropped with its arguments
+                let Some(color) = color else { continue };
+                Some((layer, color))
+            }
+            // colon separated form, e.g. `38:2::255:0:0`
+            _ if param.starts_with("38:")
+                || param.starts_with("48:")
+                || param.starts_with("58:") =>
+            {
+                let layer = layer(&param[..2]);
+                let subparams: Vec<&str> = param.split(':').collect();
+                let color = match subparams.get(1) {
+                    Some(&"5") => subparams
+                        .get(2)
+                        .and_then(|n| n.parse().ok())
+                        .map(SgrColor::Fixed),
+                    // the color space id before the channels is optional
+                    Some(&"2") if subparams.len() >= 5 => {
+                        let channels: Vec<u8> = subparams[subparams.len() - 3..]
+                            .iter()
+                            .filter_map(|c| c.parse().ok())
+                            .collect();
+                        match channels[..] {
+                            [r, g, b] => Some(SgrColor::Rgb(r, g, b)),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                };
+                // malformed, dropped with its arguments
+                let Some(color) = color else { continue };
+                Some((layer, color))
+            }
+            _ => match param.parse::<u8>() {
+                Ok(code @ 30..=37) => Some((Layer::Foreground, SgrColor::Ansi(code - 30))),
+                Ok(code @ 90..=97) => Some((Layer::Foreground, SgrColor::Ansi(code - 90 + 8))),
+                Ok(code @ 40..=47) => Some((Layer::Background, SgrColor::Ansi(code - 40))),
+                Ok(code @ 100..=107) => Some((Layer::Background, SgrColor::Ansi(code - 100 + 8))),
+                _ => None,
+            },
+        };
+        match color {
+            Some((layer, color)) => downgraded.extend(color_params(layer, color, depth)),
+            None => downgraded.push(param.to_string()),
+        }
+    }
+    downgraded.join(";")
+}
+
+fn layer(code: &str) -> Layer {
+    match code {
+        "38" => Layer::Foreground,
+        "48" => Layer::Background,
+        _ => Layer::Underline,
+    }
+}
+
+// SGR parameters setting `color` in `depth`, `None` if it can't be shown at all.
+fn color_params(layer: Layer, color: SgrColor, depth: ColorDepth) -> Option<String> {
+    match depth {
+        ColorDepth::TrueColor => Some(match color {
+            SgrColor::Ansi(n) => ansi_param(layer, n),
+            SgrColor::Fixed(n) => format!("{};5;{}", layer as u8, n),
+            SgrColor::Rgb(r, g, b) => format!("{};2;{};{};{}", layer as u8, r, g, b),
+        }),
+        ColorDepth::EightBit => Some(match color {
+            SgrColor::Ansi(n) => ansi_param(layer, n),
+            SgrColor::Fixed(n) => format!("{};5;{}", layer as u8, n),
+            SgrColor::Rgb(r, g, b) => format!("{};5;{}", layer as u8, nearest_fixed(r, g, b)),
+        }),
+        // underline colors need 256 color support
+        ColorDepth::Ansi if layer == Layer::Underline => None,
+        ColorDepth::Ansi => {
+            let n = match color {
+                SgrColor::Ansi(n) => n,
+                SgrColor::Fixed(n) if n < 16 => n,
+                SgrColor::Fixed(n) => {
+                    let (r, g, b) = fixed_to_rgb(n);
+                    nearest_ansi(r, g, b)
+                }
+                SgrColor::Rgb(r, g, b) => nearest_ansi(r, g, b),
+            };
+            Some(ansi_param(layer, n))
+        }
+        ColorDepth::None => None,
+    }
+}
+
+fn ansi_param(layer: Layer, n: u8) -> String {
+    let base = match (layer, n < 8) {
+        (Layer::Background, true) => 40,
+        (Layer::Background, false) => 100 - 8,
+        (_, true) => 30,
+        (_, false) => 90 - 8,
+    };
+    (base + n).to_string()
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).unsigned_abs().pow(2);
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+// Nearest of the 240 colors of the cube and the grayscale ramp, the first 16 depend on the
+// terminal's theme so they're left out.
+fn nearest_fixed(r: u8, g: u8, b: u8) -> u8 {
+    let level = |c: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|i| (CUBE_LEVELS[*i] as i32 - c as i32).abs())
+            .unwrap_or(0) as u8
+    };
+    let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray = 232 + ((average.saturating_sub(3)) / 10).min(23) as u8;
+    [cube, gray]
+        .into_iter()
+        .min_by_key(|n| distance(fixed_to_rgb(*n), (r, g, b)))
+        .unwrap_or(cube)
+}
+
+fn nearest_ansi(r: u8, g: u8, b: u8) -> u8 {
+    (0..ANSI_COLORS.len() as u8)
+        .min_by_key(|n| distance(ANSI_COLORS[*n as usize], (r, g, b)))
+        .unwrap_or(0)
+}
+
+fn fixed_to_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..=15 => ANSI_COLORS[n as usize],
+        16..=231 => {
+            let n = n - 16;
+            (
+                CUBE_LEVELS[(n / 36) as usize],
+                CUBE_LEVELS[(n / 6 % 6) as usize],
+                CUBE_LEVELS[(n % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + 10 * (n - 232);
+            (level, level, level)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truecolor_maps_to_nearest_of_cube_and_grayscale_ramp() {
+        // cube corners
+        assert_eq!(nearest_fixed(0, 0, 0), 16);
+        assert_eq!(nearest_fixed(255, 255, 255), 231);
+        assert_eq!(nearest_fixed(255, 0, 0), 196);
+        assert_eq!(nearest_fixed(0, 255, 255), 51);
+        // grays between the levels of the cube go to the ramp, from its first to its last step
+        assert_eq!(nearest_fixed(8, 8, 8), 232);
+        assert_eq!(nearest_fixed(128, 128, 128), 244);
+        assert_eq!(nearest_fixed(238, 238, 238), 255);
+        assert_eq!(
+            downgrade_colors("\u{1b}[38;2;255;0;0mred", ColorDepth::EightBit),
+            "\u{1b}[38;5;196mred"
+        );
+    }
+
+    #[test]
+    fn fixed_colors_map_to_nearest_ansi_color() {
+        let downgrade = |output| downgrade_colors(output, ColorDepth::Ansi);
+        assert_eq!(downgrade("\u{1b}[38;5;196m"), "\u{1b}[91m");
+        assert_eq!(downgrade("\u{1b}[38;5;232m"), "\u{1b}[30m");
+        // the first 16 are the ANSI colors themselves
+        assert_eq!(downgrade("\u{1b}[48;5;4m"), "\u{1b}[44m");
+        assert_eq!(downgrade("\u{1b}[48;5;12m"), "\u{1b}[104m");
+    }
+
+    #[test]
+    fn rewrites_color_parameters_in_place() {
+        let sgr = "\u{1b}[1;38;5;196;48;2;255;255;255mtext";
+        assert_eq!(
+            downgrade_colors(sgr, ColorDepth::EightBit),
+            "\u{1b}[1;38;5;196;48;5;231mtext"
+        );
+        assert_eq!(
+            downgrade_colors(sgr, ColorDepth::Ansi),
+            "\u{1b}[1;91;107mtext"
+        );
+        assert_eq!(
+            downgrade_colors("\u{1b}[48:2::0:0:0m", ColorDepth::Ansi),
+            "\u{1b}[40m"
+        );
+        // underline colors can't be shown with 16 colors
+        assert_eq!(
+            downgrade_colors("\u{1b}[4;58;5;196m", ColorDepth::Ansi),
+            "\u{1b}[4m"
+        );
+    }
+
+    #[test]
+    fn no_colors_keeps_attributes_and_resets() {
+        let downgrade = |output| downgrade_colors(output, ColorDepth::None);
+        assert_eq!(
+            downgrade("\u{1b}[1;38;2;1;2;3mbold\u{1b}[0m"),
+            "\u{1b}[1mbold\u{1b}[0m"
+        );
+        assert_eq!(downgrade("\u{1b}[31mred\u{1b}[m"), "red\u{1b}[m");
+        assert_eq!(downgrade("\u{1b}[44;97m"), "");
+    }
+
+    #[test]
+    fn leaves_other_parameters_and_sequences_as_is() {
+        let output = "\u{1b}[2K\u{1b}]0;title\u{7}\u{1b}[1;3;4;9;22mtext\u{1b}[0m";
+        for depth in [ColorDepth::EightBit, ColorDepth::Ansi, ColorDepth::None] {
+            assert_eq!(downgrade_colors(output, depth), output);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use unicode_width::UnicodeWidthStr;
+use zellij_tile::prelude::*;
+
+use crate::ansi::{tokenize, Token};
+use crate::config::CommandSegmentConfig;
+use crate::tab::truncate;
+
+/// Value of the `source` context key of the `RunCommandResult` events triggered by command
+/// segments, the `id` and `run` keys identify the segment and the run.
+pub const COMMAND_SEGMENT_SOURCE: &str = "command_segment";
+
+// max width of the output of command segments
+const COMMAND_OUTPUT_WIDTH: usize = 40;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum CommandOutput {
+    /// The command didn't finish yet.
+    #[default]
+    Pending,
+    /// First line of stdout, only SGR escape sequences (colors and text attributes) are kept.
+    Text {
+        text: String,
+        width: usize,
+    },
+    /// Non-zero exit code, `None` if the command was terminated by a signal.
+    Failed(Option<i32>),
+    TimedOut,
+}
+
+/// State of a command segment, the command is run every `interval` seconds.
+#[derive(Debug, Default)]
+pub struct CommandSegment {
+    pub output: CommandOutput,
+    // incremented on every run so late results of runs that timed out are ignored
+    run_id: u64,
+    // seconds since the current run started, `None` when the command isn't running
+    running_for: Option<f64>,
+    since_last_run: f64,
+}
+
+impl CommandSegment {
+    /// Advances the interval/timeout countdowns by `elapsed` seconds and runs the command when
+    /// due (and `can_run`), returns `true` if the output changed.
+    pub fn tick(&mut self, config: &CommandSegmentConfig, elapsed: f64, can_run: bool) -> bool {
+        self.since_last_run += elapsed;
+        if let Some(running_for) = &mut self.running_for {
+            *running_for += elapsed;
+            if *running_for < config.timeout {
+                return false;
+            }
+            // the command can't be killed from a plugin, its result is ignored if it ever
+            // finishes
+            self.running_for = None;
+            let changed = self.output != CommandOutput::TimedOut;
+            self.output = CommandOutput::TimedOut;
+            return changed;
+        }
+        // run right away the first time
+        if can_run && (self.run_id == 0 || self.since_last_run >= config.interval) {
+            self.run(config);
+        }
+        false
+    }
+
+    fn run(&mut self, config: &CommandSegmentConfig) {
+        self.run_id += 1;
+        self.running_for = Some(0.0);
+        self.since_last_run = 0.0;
+        let context = BTreeMap::from([
+            ("source".to_string(), COMMAND_SEGMENT_SOURCE.to_string()),
+            ("id".to_string(), config.id.clone()),
+            ("run".to_string(), self.run_id.to_string()),
+        ]);
+        run_command_with_env_variables_and_cwd(
+            &["sh", "-c", &config.command],
+            BTreeMap::new(),
+            PathBuf::from("."),
+            context,
+        );
+    }
+
+    /// Stores the result of a run, returns `true` if the output changed.
+    pub fn handle_result(&mut self, run_id: u64, exit_code: Option<i32>, stdout: &[u8]) -> bool {
+        if run_id != self.run_id || self.running_for.is_none() {
+            return false;
+        }
+        self.running_for = None;
+        let output = match exit_code {
+            Some(0) => {
+                let (text, width) = sanitize_output(stdout, COMMAND_OUTPUT_WIDTH);
+                CommandOutput::Text { text, width }
+            }
+            exit_code => CommandOutput::Failed(exit_code),
+        };
+        let changed = output != self.output;
+        self.output = output;
+        changed
+    }
+}
+
+// Keeps the first non-empty line of the output without control characters and escape sequences
+// other than SGR ones, truncated to `max_width`. Returns the text and its width on screen.
+fn sanitize_output(stdout: &[u8], max_width: usize) -> (String, usize) {
+    let stdout = String::from_utf8_lossy(stdout);
+    let line = stdout.trim().lines().next().unwrap_or_default();
+
+    // (piece, is text), SGR sequences are the only escape sequences kept
+    let mut pieces: Vec<(String, bool)> = vec![];
+    for token in tokenize(line) {
+        match token {
+            Token::Text(text) => {
+                pieces.push((text.chars().filter(|c| !c.is_control()).collect(), true));
+            }
+            Token::Csi {
+                sequence,
+                terminator: 'm',
+                ..
+            } => pieces.push((sequence.to_string(), false)),
+            _ => {}
+        }
+    }
+    let text: String = pieces
+        .iter()
+        .filter(|(_, is_text)| *is_text)
+        .map(|(piece, _)| piece.as_str())
+        .collect();
+    let width = text.width();
+    if width <= max_width {
+        return (pieces.into_iter().map(|(piece, _)| piece).collect(), width);
+    }
+
+    // the truncated text is a prefix of `text` followed by an ellipsis, the escape sequences
+    // before the cut are kept
+    let truncated = truncate(&text, max_width);
+    let mut kept = truncated.len() - '…'.len_utf8();
+    let mut output = String::new();
+    for (piece, is_text) in pieces {
+        if !is_text {
+            output.push_str(&piece);
+            continue;
+        }
+        let end = kept.min(piece.len());
+        output.push_str(&piece[..end]);
+        kept -= end;
+        if kept == 0 {
+            break;
+        }
+    }
+    output.push('…');
+    (output, truncated.width())
+}
@@ -0,0 +1,337 @@
+use std::collections::BTreeMap;
+
+use crate::clock::parse_utc_offset;
+use crate::color::ColorDepth;
+use crate::rule::TabRule;
+
+static DEFAULT_SILENCE_THRESHOLD: f64 = 30.0;
+static DEFAULT_FOCUSED_PANE_FORMAT: &str = "{title}";
+static DEFAULT_FOCUSED_PANE_MAX_WIDTH: usize = 40;
+static DEFAULT_FULLSCREEN_ICON: &str = "⤢";
+static DEFAULT_SYNC_ICON: &str = "⇄";
+static DEFAULT_GIT_REFRESH_INTERVAL: f64 = 10.0;
+static DEFAULT_COMMAND_SEGMENT_INTERVAL: f64 = 10.0;
+static DEFAULT_COMMAND_SEGMENT_TIMEOUT: f64 = 5.0;
+
+#[derive(Debug)]
+pub struct Config {
+    pub monitor_activity: TabFilter,
+    /// Seconds a pane has to stay quiet before its tab is marked as silent.
+    pub silence_threshold: f64,
+    /// Jobs finishing faster than this (in seconds) don't raise an alert.
+    pub alert_min_duration: f64,
+    /// Command run through `sh -c` every time a tab alert is raised.
+    pub alert_hook: Option<String>,
+    /// Show the panes of the active tab.
+    pub pane_list: bool,
+    /// Show the title of the focused pane of the active tab.
+    pub focused_pane: bool,
+    /// Template for the focused pane segment, supports `{title}`, `{command}`, `{cwd}` and
+    /// `{id}`.
+    pub focused_pane_format: String,
+    pub focused_pane_max_width: usize,
+    /// Show the number of tiled and floating panes on each tab.
+    pub pane_counts: bool,
+    /// Rendered on tabs with a fullscreen pane, an empty string disables it.
+    pub fullscreen_icon: String,
+    /// Rendered on tabs with synced panes, an empty string disables it.
+    pub sync_icon: String,
+    /// Colors/icons for tabs matching name patterns, the first matching rule applies.
+    pub tab_rules: Vec<TabRule>,
+    /// Show the most useful keybindings of the current mode.
+    pub keybind_hints: bool,
+    /// `strftime`-like format of the clock segment, the clock is hidden when unset.
+    pub clock_format: Option<String>,
+    /// Offset from UTC (in seconds) of the time shown by the clock.
+    pub clock_utc_offset: i64,
+    pub session_overview: SessionOverview,
+    /// Show the git branch of the focused pane's working directory.
+    pub git_status: bool,
+    /// Seconds between refreshes of the git branch while the focused pane stays the same.
+    pub git_refresh_interval: f64,
+    /// Where segments pushed through the `zj-status-bar:cli:segment` pipe are rendered.
+    pub pushed_segments_zone: SegmentZone,
+    /// Segments showing the output of commands, ordered by id.
+    pub command_segments: Vec<CommandSegmentConfig>,
+    pub tab_alignment: TabAlignment,
+    /// Leave the background of the bar to the terminal, only blocks (tabs, segments) are painted.
+    pub transparent_background: bool,
+    pub color_depth: ColorDepth,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            monitor_activity: TabFilter::default(),
+            silence_threshold: DEFAULT_SILENCE_THRESHOLD,
+            alert_min_duration: 0.0,
+            alert_hook: None,
+            pane_list: false,
+            focused_pane: false,
+            focused_pane_format: DEFAULT_FOCUSED_PANE_FORMAT.to_string(),
+            focused_pane_max_width: DEFAULT_FOCUSED_PANE_MAX_WIDTH,
+            pane_counts: false,
+            fullscreen_icon: DEFAULT_FULLSCREEN_ICON.to_string(),
+            sync_icon: DEFAULT_SYNC_ICON.to_string(),
+            tab_rules: vec![],
+            keybind_hints: false,
+            clock_format: None,
+            clock_utc_offset: 0,
+            session_overview: SessionOverview::default(),
+            git_status: false,
+            git_refresh_interval: DEFAULT_GIT_REFRESH_INTERVAL,
+            pushed_segments_zone: SegmentZone::default(),
+            command_segments: vec![],
+            tab_alignment: TabAlignment::default(),
+            transparent_background: false,
+            color_depth: ColorDepth::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn new(configuration: &BTreeMap<String, String>) -> Self {
+        Config {
+            monitor_activity: configuration
+                .get("monitor_activity")
+                .map(|value| TabFilter::parse(value))
+                .unwrap_or_default(),
+            silence_threshold: configuration
+                .get("silence_threshold")
+                .and_then(|value| value.parse().ok())
+                .filter(|threshold: &f64| *threshold > 0.0 && threshold.is_finite())
+                .unwrap_or(DEFAULT_SILENCE_THRESHOLD),
+            alert_min_duration: configuration
+                .get("alert_min_duration")
+                .and_then(|value| value.parse().ok())
+                .filter(|duration: &f64| *duration >= 0.0 && duration.is_finite())
+                .unwrap_or(0.0),
+            alert_hook: configuration
+                .get("alert_hook")
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty()),
+            pane_list: configuration
+                .get("pane_list")
+                .is_some_and(|value| value.trim() == "true"),
+            focused_pane: configuration
+                .get("focused_pane")
+                .is_some_and(|value| value.trim() == "true"),
+            focused_pane_format: configuration
+                .get("focused_pane_format")
+                .cloned()
+                .unwrap_or_else(|| DEFAULT_FOCUSED_PANE_FORMAT.to_string()),
+            focused_pane_max_width: configuration
+                .get("focused_pane_max_width")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(DEFAULT_FOCUSED_PANE_MAX_WIDTH),
+            pane_counts: configuration
+                .get("pane_counts")
+                .is_some_and(|value| value.trim() == "true"),
+            fullscreen_icon: configuration
+                .get("fullscreen_icon")
+                .map(|value| value.trim().to_string())
+                .unwrap_or_else(|| DEFAULT_FULLSCREEN_ICON.to_string()),
+            sync_icon: configuration
+                .get("sync_icon")
+                .map(|value| value.trim().to_string())
+                .unwrap_or_else(|| DEFAULT_SYNC_ICON.to_string()),
+            tab_rules: configuration
+                .get("tab_rules")
+                .map(|value| TabRule::parse_rules(value))
+                .unwrap_or_default(),
+            keybind_hints: configuration
+                .get("keybind_hints")
+                .is_some_and(|value| value.trim() == "true"),
+            clock_format: configuration
+                .get("clock_format")
+                .filter(|value| !value.trim().is_empty())
+                .cloned(),
+            clock_utc_offset: configuration
+                .get("clock_utc_offset")
+                .and_then(|value| {
+                    let offset = parse_utc_offset(value);
+                    if offset.is_none() {
+                        eprintln!("Ignoring invalid clock_utc_offset: {}", value.trim());
+                    }
+                    offset
+                })
+                .unwrap_or(0),
+            session_overview: configuration
+                .get("session_overview")
+                .map(|value| SessionOverview::parse(value))
+                .unwrap_or_default(),
+            git_status: configuration
+                .get("git_status")
+                .is_some_and(|value| value.trim() == "true"),
+            git_refresh_interval: configuration
+                .get("git_refresh_interval")
+                .and_then(|value| value.parse().ok())
+                .filter(|interval: &f64| *interval > 0.0 && interval.is_finite())
+                .unwrap_or(DEFAULT_GIT_REFRESH_INTERVAL),
+            pushed_segments_zone: configuration
+                .get("pushed_segments_zone")
+                .map(|value| SegmentZone::parse(value))
+                .unwrap_or_default(),
+            command_segments: CommandSegmentConfig::parse_all(configuration),
+            tab_alignment: configuration
+                .get("tab_alignment")
+                .map(|value| TabAlignment::parse(value))
+                .unwrap_or_default(),
+            transparent_background: configuration
+                .get("transparent_background")
+                .is_some_and(|value| value.trim() == "true"),
+            color_depth: configuration
+                .get("color_depth")
+                .map(|value| ColorDepth::parse(value))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Segment showing the first line of the output of a command run every `interval` seconds.
+///
+/// Defined by `command_segment_<id>` options, with the optional `command_segment_interval_<id>`
+/// and `command_segment_timeout_<id>` options (in seconds).
+#[derive(Debug)]
+pub struct CommandSegmentConfig {
+    pub id: String,
+    /// Run through `sh -c`.
+    pub command: String,
+    pub interval: f64,
+    /// Seconds after which the command is considered stuck.
+    pub timeout: f64,
+}
+
+impl CommandSegmentConfig {
+    fn parse_all(configuration: &BTreeMap<String, String>) -> Vec<Self> {
+        let seconds = |option: &str, id: &str, default: f64| {
+            configuration
+                .get(&format!("command_segment_{}_{}", option, id))
+                .and_then(|value| value.trim().parse().ok())
+                .filter(|value: &f64| *value > 0.0 && value.is_finite())
+                .unwrap_or(default)
+        };
+        configuration
+            .iter()
+            .filter_map(|(key, command)| {
+                let id = key.strip_prefix("command_segment_")?;
+                if id.starts_with("interval_") || id.starts_with("timeout_") {
+                    return None;
+                }
+                if command.trim().is_empty() {
+                    return None;
+                }
+                Some(CommandSegmentConfig {
+                    id: id.to_string(),
+                    command: command.clone(),
+                    interval: seconds("interval", id, DEFAULT_COMMAND_SEGMENT_INTERVAL),
+                    timeout: seconds("timeout", id, DEFAULT_COMMAND_SEGMENT_TIMEOUT),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Position of the tabs in the space left between the mode and the segments, parsed from `left`,
+/// `center` or `right`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum TabAlignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl TabAlignment {
+    fn parse(value: &str) -> Self {
+        match value.trim() {
+            "center" | "centered" => TabAlignment::Center,
+            "right" => TabAlignment::Right,
+            _ => TabAlignment::Left,
+        }
+    }
+
+    /// Part of the `free_space` that goes before the tabs.
+    pub fn leading_space(self, free_space: usize) -> usize {
+        match self {
+            TabAlignment::Left => 0,
+            TabAlignment::Center => free_space / 2,
+            TabAlignment::Right => free_space,
+        }
+    }
+}
+
+/// Part of the bar a group of segments is rendered in, parsed from `left` or `right`.
+#[derive(Debug, Default, PartialEq)]
+pub enum SegmentZone {
+    /// After the session name and mode, before the tabs.
+    Left,
+    /// With the other segments, before the swap layout name.
+    #[default]
+    Right,
+}
+
+impl SegmentZone {
+    fn parse(value: &str) -> Self {
+        match value.trim() {
+            "left" => SegmentZone::Left,
+            _ => SegmentZone::Right,
+        }
+    }
+}
+
+/// How other sessions are shown, parsed from `count`, `list` or `false`.
+#[derive(Debug, Default, PartialEq)]
+pub enum SessionOverview {
+    #[default]
+    Hidden,
+    /// Number of other live and resurrectable sessions.
+    Count,
+    /// Names of the other live sessions (clickable) and number of resurrectable ones.
+    List,
+}
+
+impl SessionOverview {
+    fn parse(value: &str) -> Self {
+        match value.trim() {
+            "count" | "true" => SessionOverview::Count,
+            "list" => SessionOverview::List,
+            _ => SessionOverview::Hidden,
+        }
+    }
+}
+
+/// Set of tabs a per-tab option applies to.
+///
+/// Parsed from `true`/`*` (all tabs), `false` (no tabs) or a comma-separated list of tab names.
+#[derive(Debug, Default)]
+pub enum TabFilter {
+    #[default]
+    None,
+    All,
+    Names(Vec<String>),
+}
+
+impl TabFilter {
+    fn parse(value: &str) -> Self {
+        match value.trim() {
+            "" | "false" => TabFilter::None,
+            "true" | "*" => TabFilter::All,
+            names => TabFilter::Names(
+                names
+                    .split(',')
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty())
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn matches(&self, tab_name: &str) -> bool {
+        match self {
+            TabFilter::None => false,
+            TabFilter::All => true,
+            TabFilter::Names(names) => names.iter().any(|name| name == tab_name),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use zellij_tile::prelude::*;
+
+/// Value of the `source` context key of the `RunCommandResult` events triggered by git status
+/// refreshes.
+pub const GIT_STATUS_SOURCE: &str = "git_status";
+
+// `~` isn't expanded by zellij when running commands, the directory is passed as an environment
+// variable so it never gets evaluated by the shell.
+const GIT_STATUS_SCRIPT: &str = r#"case "$ZJ_CWD" in
+  "~"*) cd "$HOME${ZJ_CWD#"~"}" ;;
+  *) cd "$ZJ_CWD" ;;
+esac || exit 1
+exec git status --porcelain=v2 --branch --untracked-files=no"#;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitStatus {
+    /// Branch name or abbreviated commit hash on a detached HEAD.
+    pub branch: String,
+    /// Tracked files have uncommitted changes.
+    pub dirty: bool,
+}
+
+/// Directory of a pane whose shell doesn't report it, shells commonly set the pane title to the
+/// working directory (e.g. `~/code/project`).
+pub fn cwd_from_title(title: &str) -> Option<&str> {
+    let title = title.trim();
+    (title.starts_with('/') || title == "~" || title.starts_with("~/")).then_some(title)
+}
+
+/// Working directory of a terminal pane, as reported through the `pane_cwd` pipe or guessed from
+/// its title.
+pub fn pane_cwd<'a>(pane_cwds: &'a HashMap<u32, String>, pane: &'a PaneInfo) -> Option<&'a str> {
+    match pane_cwds.get(&pane.id) {
+        Some(cwd) => Some(cwd),
+        None => cwd_from_title(&pane.title),
+    }
+}
+
+pub fn run_git_status(cwd: &str) {
+    let env_variables = BTreeMap::from([("ZJ_CWD".to_string(), cwd.to_string())]);
+    let context = BTreeMap::from([
+        ("source".to_string(), GIT_STATUS_SOURCE.to_string()),
+        ("cwd".to_string(), cwd.to_string()),
+    ]);
+    run_command_with_env_variables_and_cwd(
+        &["sh", "-c", GIT_STATUS_SCRIPT],
+        env_variables,
+        PathBuf::from("."),
+        context,
+    );
+}
+
+/// Parses the output of `git status --porcelain=v2 --branch`, returns `None` if the command failed
+/// (e.g. the directory isn't in a git repository).
+pub fn parse_git_status(exit_code: Option<i32>, stdout: &[u8]) -> Option<GitStatus> {
+    if exit_code != Some(0) {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(stdout);
+    let mut head = None;
+    let mut oid = None;
+    let mut dirty = false;
+    for line in stdout.lines() {
+        if let Some(value) = line.strip_prefix("# branch.head ") {
+            head = Some(value);
+        } else if let Some(value) = line.strip_prefix("# branch.oid ") {
+            oid = Some(value);
+        } else if !line.starts_with('#') && !line.is_empty() {
+            dirty = true;
+        }
+    }
+    let branch = match head? {
+        "(detached)" => oid?.chars().take(7).collect(),
+        head => head.to_string(),
+    };
+    Some(GitStatus { branch, dirty })
+}
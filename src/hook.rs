@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use zellij_tile::prelude::*;
+
+/// Value of the `source` context key of the `RunCommandResult` events triggered by alert hooks.
+pub const ALERT_HOOK_SOURCE: &str = "alert_hook";
+
+/// Alert being reported to the user's hook command.
+pub struct AlertHookEvent<'a> {
+    pub tab_name: &'a str,
+    pub pane_id: u32,
+    pub exit_code: i32,
+    pub message: &'a str,
+}
+
+/// Runs the configured hook command through `sh -c`, details of the alert are passed as
+/// environment variables so they can be used from the command (e.g. `notify-send "$ZJ_MESSAGE"`).
+pub fn run_alert_hook(command: &str, event: AlertHookEvent) {
+    let env_variables = BTreeMap::from([
+        ("ZJ_TAB_NAME".to_string(), event.tab_name.to_string()),
+        ("ZJ_PANE_ID".to_string(), event.pane_id.to_string()),
+        ("ZJ_EXIT_CODE".to_string(), event.exit_code.to_string()),
+        ("ZJ_MESSAGE".to_string(), event.message.to_string()),
+    ]);
+    let context = BTreeMap::from([
+        ("source".to_string(), ALERT_HOOK_SOURCE.to_string()),
+        ("tab_name".to_string(), event.tab_name.to_string()),
+    ]);
+    run_command_with_env_variables_and_cwd(
+        &["sh", "-c", command],
+        env_variables,
+        PathBuf::from("."),
+        context,
+    );
+}
+
+/// Logs hook failures, successful runs are silent.
+pub fn report_alert_hook_result(
+    exit_code: Option<i32>,
+    stderr: &[u8],
+    context: &BTreeMap<String, String>,
+) {
+    let tab_name = context.get("tab_name").map_or("", String::as_str);
+    let stderr = String::from_utf8_lossy(stderr);
+    match exit_code {
+        Some(0) => {}
+        Some(exit_code) => eprintln!(
+            "Alert hook for tab \"{}\" failed with exit code {}: {}",
+            tab_name,
+            exit_code,
+            stderr.trim()
+        ),
+        None => eprintln!(
+            "Alert hook for tab \"{}\" was terminated: {}",
+            tab_name,
+            stderr.trim()
+        ),
+    }
+}
@@ -1,25 +1,51 @@
-use ansi_term::ANSIStrings;
+use std::collections::VecDeque;
+
 use unicode_width::UnicodeWidthStr;
 
+use crate::config::TabAlignment;
+use crate::styled::{style, StyledText};
+use crate::tab::{TabCompression, TabIndicators};
 use crate::{LinePart, ARROW_SEPARATOR};
 use zellij_tile::prelude::*;
-use zellij_tile_utils::style;
+
+/// Everything [`tab_lines`] depends on, the layout is only computed again when any of it changes.
+/// Tabs are rendered from their `TabInfo`, label and indicators.
+#[derive(Debug, PartialEq)]
+pub struct LayoutKey {
+    pub tabs: Vec<(TabInfo, String, TabIndicators)>,
+    pub session_name: Option<String>,
+    pub left_segments: Vec<LinePart>,
+    pub segments: Vec<LinePart>,
+    pub alignment: TabAlignment,
+    pub active_tab_index: usize,
+    pub rows: usize,
+    pub cols: usize,
+    pub palette: Palette,
+    pub background: Option<PaletteColor>,
+    pub capabilities: PluginCapabilities,
+    pub hide_session_name: bool,
+    pub mode: InputMode,
+    pub swap_layout_name: Option<String>,
+    pub is_swap_layout_dirty: bool,
+}
 
 fn get_current_title_len(current_title: &[LinePart]) -> usize {
     current_title.iter().map(|p| p.len).sum()
 }
 
-// move elements from before_active and after_active into tabs_to_render while they fit in cols
-// adds collapsed_tabs to the left and right if there's left over tabs that don't fit
+/// Adds tabs around the active one as long as they fit. `tabs_after_active` is in reverse order so
+/// both sides are consumed by popping, and tabs are added in front through a `VecDeque`, which
+/// keeps this linear in the number of tabs.
 fn populate_tabs_in_tab_line(
     tabs_before_active: &mut Vec<LinePart>,
     tabs_after_active: &mut Vec<LinePart>,
-    tabs_to_render: &mut Vec<LinePart>,
+    tabs_to_render: &mut VecDeque<LinePart>,
     cols: usize,
     palette: Palette,
+    background: Option<PaletteColor>,
     capabilities: PluginCapabilities,
 ) {
-    let mut middle_size = get_current_title_len(tabs_to_render);
+    let mut middle_size: usize = tabs_to_render.iter().map(|part| part.len).sum();
 
     let mut total_left = 0;
     let mut total_right = 0;
@@ -32,6 +58,7 @@ fn populate_tabs_in_tab_line(
         let collapsed_left = left_more_message(
             left_count,
             palette,
+            background,
             tab_separator(capabilities),
             left_more_tab_index,
         );
@@ -40,6 +67,7 @@ fn populate_tabs_in_tab_line(
         let collapsed_right = right_more_message(
             right_count,
             palette,
+            background,
             tab_separator(capabilities),
             right_more_tab_index,
         );
@@ -57,7 +85,7 @@ fn populate_tabs_in_tab_line(
             usize::MAX
         };
 
-        let right = if let Some(tab) = tabs_after_active.first() {
+        let right = if let Some(tab) = tabs_after_active.last() {
             tab.len
         } else {
             usize::MAX
@@ -89,17 +117,17 @@ fn populate_tabs_in_tab_line(
             let tab = tabs_before_active.pop().unwrap();
             middle_size += tab.len;
             total_left += tab.len;
-            tabs_to_render.insert(0, tab);
+            tabs_to_render.push_front(tab);
         } else if right_fits {
             // add right tab
-            let tab = tabs_after_active.remove(0);
+            let tab = tabs_after_active.pop().unwrap();
             middle_size += tab.len;
             total_right += tab.len;
-            tabs_to_render.push(tab);
+            tabs_to_render.push_back(tab);
         } else {
             // there's either no space to add more tabs or no more tabs to add, so we're done
-            tabs_to_render.insert(0, collapsed_left);
-            tabs_to_render.push(collapsed_right);
+            tabs_to_render.push_front(collapsed_left);
+            tabs_to_render.push_back(collapsed_right);
             break;
         }
     }
@@ -108,6 +136,7 @@ fn populate_tabs_in_tab_line(
 fn left_more_message(
     tab_count_to_the_left: usize,
     palette: Palette,
+    background: Option<PaletteColor>,
     separator: &str,
     tab_index: usize,
 ) -> LinePart {
@@ -119,28 +148,13 @@ fn left_more_message(
     } else {
         " ← +many ".to_string()
     };
-    // 238
-    // chars length plus separator length on both sides
-    let more_text_len = more_text.width() + 2 * separator.width();
-    let (text_color, sep_color) = match palette.theme_hue {
-        ThemeHue::Dark => (palette.white, palette.black),
-        ThemeHue::Light => (palette.black, palette.white),
-    };
-    let left_separator = style!(sep_color, palette.orange).paint(separator);
-    let more_styled_text = style!(text_color, palette.orange).bold().paint(more_text);
-    let right_separator = style!(palette.orange, sep_color).paint(separator);
-    let more_styled_text =
-        ANSIStrings(&[left_separator, more_styled_text, right_separator]).to_string();
-    LinePart {
-        part: more_styled_text,
-        len: more_text_len,
-        tab_index: Some(tab_index),
-    }
+    more_message(&more_text, palette, background, separator, tab_index)
 }
 
 fn right_more_message(
     tab_count_to_the_right: usize,
     palette: Palette,
+    background: Option<PaletteColor>,
     separator: &str,
     tab_index: usize,
 ) -> LinePart {
@@ -152,21 +166,29 @@ fn right_more_message(
     } else {
         " +many → ".to_string()
     };
-    // chars length plus separator length on both sides
-    let more_text_len = more_text.width() + 2 * separator.width();
-    let (text_color, sep_color) = match palette.theme_hue {
-        ThemeHue::Dark => (palette.white, palette.black),
-        ThemeHue::Light => (palette.black, palette.white),
+    more_message(&more_text, palette, background, separator, tab_index)
+}
+
+// collapsed tabs on either side, clicking on them focuses the tab right next to the visible ones
+fn more_message(
+    more_text: &str,
+    palette: Palette,
+    background: Option<PaletteColor>,
+    separator: &str,
+    tab_index: usize,
+) -> LinePart {
+    let text_color = match palette.theme_hue {
+        ThemeHue::Dark => palette.white,
+        ThemeHue::Light => palette.black,
     };
-    let left_separator = style!(sep_color, palette.orange).paint(separator);
-    let more_styled_text = style!(text_color, palette.orange).bold().paint(more_text);
-    let right_separator = style!(palette.orange, sep_color).paint(separator);
-    let more_styled_text =
-        ANSIStrings(&[left_separator, more_styled_text, right_separator]).to_string();
+    let mut more_styled_text = StyledText::default();
+    more_styled_text
+        .push_left_separator(separator, palette.orange, background)
+        .push(style(text_color, Some(palette.orange)).bold(), more_text)
+        .push_right_separator(separator, palette.orange, background);
     LinePart {
-        part: more_styled_text,
-        len: more_text_len,
         tab_index: Some(tab_index),
+        ..more_styled_text.into_line_part()
     }
 }
 
@@ -174,13 +196,9 @@ fn tab_line_prefix(
     session_name: Option<&str>,
     mode: InputMode,
     palette: Palette,
+    background: Option<PaletteColor>,
     _cols: usize,
 ) -> Vec<LinePart> {
-    let bg_color = match palette.theme_hue {
-        ThemeHue::Dark => palette.black,
-        ThemeHue::Light => palette.white,
-    };
-
     let locked_mode_color = palette.magenta;
     let normal_mode_color = palette.green;
     let other_modes_color = palette.orange;
@@ -188,18 +206,16 @@ fn tab_line_prefix(
     let mut parts: Vec<LinePart> = vec![];
 
     if let Some(name) = session_name {
-        let name_part = format!(" {} |", name);
-        let name_part_len = name_part.width();
         let text_color = match palette.theme_hue {
             ThemeHue::Dark => palette.white,
             ThemeHue::Light => palette.black,
         };
-        let name_part_styled_text = style!(text_color, bg_color).bold().paint(name_part);
-        parts.push(LinePart {
-            part: name_part_styled_text.to_string(),
-            len: name_part_len,
-            tab_index: None,
-        })
+        let mut name_part = StyledText::default();
+        name_part.push(
+            style(text_color, background).bold(),
+            &format!(" {} |", name),
+        );
+        parts.push(name_part.into_line_part());
     }
     let mode_part = format!("{:?}", mode).to_uppercase();
     let mode_part_padded = format!(
@@ -207,25 +223,16 @@ fn tab_line_prefix(
         mode_part,
         mode_part_width = mode_part.width() + 2
     );
-    let mode_part_len = mode_part_padded.width();
-    let mode_part_styled_text = if mode == InputMode::Locked {
-        style!(locked_mode_color, bg_color)
-            .bold()
-            .paint(mode_part_padded)
+    let mode_color = if mode == InputMode::Locked {
+        locked_mode_color
     } else if mode == InputMode::Normal {
-        style!(normal_mode_color, bg_color)
-            .bold()
-            .paint(mode_part_padded)
+        normal_mode_color
     } else {
-        style!(other_modes_color, bg_color)
-            .bold()
-            .paint(mode_part_padded)
+        other_modes_color
     };
-    parts.push(LinePart {
-        part: format!("{}", mode_part_styled_text),
-        len: mode_part_len,
-        tab_index: None,
-    });
+    let mut mode_part_styled_text = StyledText::default();
+    mode_part_styled_text.push(style(mode_color, background).bold(), &mode_part_padded);
+    parts.push(mode_part_styled_text.into_line_part());
     parts
 }
 
@@ -237,37 +244,160 @@ pub fn tab_separator(capabilities: PluginCapabilities) -> &'static str {
     }
 }
 
-pub fn tab_line(
+/// Lays out the prefix, tabs, segments and swap layout status in up to `rows` lines.
+///
+/// `left_segments` are rendered right after the prefix and `segments` before the swap layout
+/// status, as long as they fit. The space left in each line is placed around the tabs according
+/// to `alignment`, `background` is `None` in transparent mode.
+///
+/// With more than one row tabs wrap onto the following lines, falling back to a single line
+/// with collapsed tabs if they don't fit even when fully compressed.
+#[allow(clippy::too_many_arguments)]
+pub fn tab_lines(
     session_name: Option<&str>,
-    mut all_tabs: Vec<LinePart>,
+    mut render_tabs: impl FnMut(TabCompression) -> Vec<LinePart>,
+    left_segments: Vec<LinePart>,
+    segments: Vec<LinePart>,
+    alignment: TabAlignment,
     active_tab_index: usize,
+    rows: usize,
     cols: usize,
     palette: Palette,
+    background: Option<PaletteColor>,
     capabilities: PluginCapabilities,
     hide_session_name: bool,
     mode: InputMode,
     active_swap_layout_name: &Option<String>,
     is_swap_layout_dirty: bool,
-) -> Vec<LinePart> {
-    let mut tabs_after_active = all_tabs.split_off(active_tab_index);
-    let mut tabs_before_active = all_tabs;
-    let active_tab = if !tabs_after_active.is_empty() {
-        tabs_after_active.remove(0)
+) -> Vec<Vec<LinePart>> {
+    let mut prefix = match hide_session_name {
+        true => tab_line_prefix(None, mode, palette, background, cols),
+        false => tab_line_prefix(session_name, mode, palette, background, cols),
+    };
+    let mut prefix_len = get_current_title_len(&prefix);
+    for segment in left_segments {
+        if prefix_len + segment.len > cols {
+            break;
+        }
+        prefix_len += segment.len;
+        prefix.push(segment);
+    }
+
+    // index of the first tab in the first line, the other lines only have tabs
+    let tabs_start = prefix.len();
+    let wrapped_lines = if rows > 1 {
+        wrap_tabs(&prefix, &mut render_tabs, rows, cols)
     } else {
-        tabs_before_active.pop().unwrap()
+        None
     };
-    let mut prefix = match hide_session_name {
-        true => tab_line_prefix(None, mode, palette, cols),
-        false => tab_line_prefix(session_name, mode, palette, cols),
+    let mut lines = match wrapped_lines {
+        Some(lines) => lines,
+        None => vec![tab_line(
+            prefix,
+            &mut render_tabs,
+            active_tab_index,
+            cols,
+            palette,
+            background,
+            capabilities,
+        )],
     };
+
+    if let Some((last_line, other_lines)) = lines.split_last_mut() {
+        // only the first line has the prefix
+        let last_line_tabs_start = if other_lines.is_empty() {
+            tabs_start
+        } else {
+            0
+        };
+        for (index, line) in other_lines.iter_mut().enumerate() {
+            let free_space = cols.saturating_sub(get_current_title_len(line));
+            align_tabs(
+                line,
+                if index == 0 { tabs_start } else { 0 },
+                alignment.leading_space(free_space),
+                background,
+            );
+        }
+        push_status(
+            last_line,
+            last_line_tabs_start,
+            cols,
+            alignment,
+            segments,
+            active_swap_layout_name,
+            is_swap_layout_dirty,
+            mode,
+            palette,
+            background,
+            capabilities,
+        );
+    }
+    lines
+}
+
+// place tabs one after the other, moving to the next line when they don't fit in the current one
+// returns `None` if they need more than `rows` lines even when fully compressed
+fn wrap_tabs(
+    prefix: &[LinePart],
+    render_tabs: &mut impl FnMut(TabCompression) -> Vec<LinePart>,
+    rows: usize,
+    cols: usize,
+) -> Option<Vec<Vec<LinePart>>> {
+    'compression: for compression in TabCompression::ALL {
+        let mut lines = vec![prefix.to_vec()];
+        let mut line_len = get_current_title_len(prefix);
+        for tab in render_tabs(compression) {
+            if line_len + tab.len > cols {
+                if lines.len() == rows || tab.len > cols {
+                    continue 'compression;
+                }
+                lines.push(vec![]);
+                line_len = 0;
+            }
+            line_len += tab.len;
+            lines.last_mut().unwrap().push(tab);
+        }
+        return Some(lines);
+    }
+    None
+}
+
+fn tab_line(
+    mut prefix: Vec<LinePart>,
+    render_tabs: &mut impl FnMut(TabCompression) -> Vec<LinePart>,
+    active_tab_index: usize,
+    cols: usize,
+    palette: Palette,
+    background: Option<PaletteColor>,
+    capabilities: PluginCapabilities,
+) -> Vec<LinePart> {
     let prefix_len = get_current_title_len(&prefix);
 
+    // shrink all tab labels step by step, tabs are only collapsed if they still don't fit when
+    // fully compressed
+    let mut all_tabs = vec![];
+    for compression in TabCompression::ALL {
+        all_tabs = render_tabs(compression);
+        if prefix_len + get_current_title_len(&all_tabs) <= cols {
+            break;
+        }
+    }
+
+    let mut tabs_after_active = all_tabs.split_off(active_tab_index);
+    tabs_after_active.reverse();
+    let mut tabs_before_active = all_tabs;
+    let active_tab = match tabs_after_active.pop() {
+        Some(tab) => tab,
+        None => tabs_before_active.pop().unwrap(),
+    };
+
     // if active tab alone won't fit in cols, don't draw any tabs
     if prefix_len + active_tab.len > cols {
         return prefix;
     }
 
-    let mut tabs_to_render = vec![active_tab];
+    let mut tabs_to_render = VecDeque::from([active_tab]);
 
     populate_tabs_in_tab_line(
         &mut tabs_before_active,
@@ -275,36 +405,96 @@ pub fn tab_line(
         &mut tabs_to_render,
         cols.saturating_sub(prefix_len),
         palette,
+        background,
         capabilities,
     );
-    prefix.append(&mut tabs_to_render);
+    prefix.extend(tabs_to_render);
+    prefix
+}
+
+fn filler(len: usize, background: Option<PaletteColor>) -> LinePart {
+    let mut buffer = StyledText::default();
+    match background {
+        Some(background) => buffer.push(style(background, Some(background)), &" ".repeat(len)),
+        None => buffer.push_styled(&" ".repeat(len), len),
+    };
+    buffer.into_line_part()
+}
 
-    let current_title_len = get_current_title_len(&prefix);
+// moves the tabs (starting at `tabs_start`) `leading_space` columns to the right, the filler
+// has no tab index so clicks on it don't switch tabs
+fn align_tabs(
+    line: &mut Vec<LinePart>,
+    tabs_start: usize,
+    leading_space: usize,
+    background: Option<PaletteColor>,
+) {
+    if leading_space > 0 {
+        line.insert(
+            tabs_start.min(line.len()),
+            filler(leading_space, background),
+        );
+    }
+}
+
+// fills the rest of the line and adds the segments and swap layout status at the end, as long
+// as they fit. The swap layout status and the last segment (the clock when it's enabled) are kept
+// first, then the other segments in order until one doesn't fit, the ones after it are dropped.
+#[allow(clippy::too_many_arguments)]
+fn push_status(
+    line: &mut Vec<LinePart>,
+    tabs_start: usize,
+    cols: usize,
+    alignment: TabAlignment,
+    mut segments: Vec<LinePart>,
+    active_swap_layout_name: &Option<String>,
+    is_swap_layout_dirty: bool,
+    mode: InputMode,
+    palette: Palette,
+    background: Option<PaletteColor>,
+    capabilities: PluginCapabilities,
+) {
+    let current_title_len = get_current_title_len(line);
     if current_title_len < cols {
         let mut remaining_space = cols - current_title_len;
-        if let Some(swap_layout_status) = swap_layout_status(
+        let swap_layout_status = swap_layout_status(
             remaining_space,
             active_swap_layout_name,
             is_swap_layout_dirty,
             mode,
             &palette,
+            background,
             tab_separator(capabilities),
-        ) {
+        );
+        if let Some(swap_layout_status) = &swap_layout_status {
             remaining_space -= swap_layout_status.len;
-            let mut buffer = String::new();
-            for _ in 0..remaining_space {
-                buffer.push_str(&style!(palette.black, palette.black).paint(" ").to_string());
+        }
+        let last_segment = segments
+            .pop()
+            .filter(|segment| segment.len <= remaining_space);
+        if let Some(last_segment) = &last_segment {
+            remaining_space -= last_segment.len;
+        }
+        let mut fitting_segments = vec![];
+        for segment in segments {
+            if segment.len > remaining_space {
+                break;
             }
-            prefix.push(LinePart {
-                part: buffer,
-                len: remaining_space,
-                tab_index: None,
-            });
-            prefix.push(swap_layout_status);
+            remaining_space -= segment.len;
+            fitting_segments.push(segment);
+        }
+        fitting_segments.extend(last_segment);
+        let leading_space = alignment.leading_space(remaining_space);
+        align_tabs(line, tabs_start, leading_space, background);
+        remaining_space -= leading_space;
+        if swap_layout_status.is_none() && fitting_segments.is_empty() {
+            return;
         }
-    }
 
-    prefix
+        line.push(filler(remaining_space, background));
+        line.append(&mut fitting_segments);
+        line.extend(swap_layout_status);
+    }
 }
 
 fn swap_layout_status(
@@ -313,62 +503,228 @@ fn swap_layout_status(
     is_swap_layout_damaged: bool,
     input_mode: InputMode,
     palette: &Palette,
+    background: Option<PaletteColor>,
     separator: &str,
 ) -> Option<LinePart> {
-    match swap_layout_name {
-        Some(swap_layout_name) => {
-            let mut swap_layout_name = format!(" {} ", swap_layout_name);
-            swap_layout_name.make_ascii_uppercase();
-            let swap_layout_name_len = swap_layout_name.len() + 3;
-
-            let (prefix_separator, swap_layout_name, suffix_separator) =
-                if input_mode == InputMode::Locked {
-                    (
-                        style!(palette.black, palette.fg).paint(separator),
-                        style!(palette.black, palette.fg)
-                            .italic()
-                            .paint(&swap_layout_name),
-                        style!(palette.fg, palette.black).paint(separator),
-                    )
-                } else if is_swap_layout_damaged {
-                    (
-                        style!(palette.black, palette.fg).paint(separator),
-                        style!(palette.black, palette.fg)
-                            .bold()
-                            .paint(&swap_layout_name),
-                        style!(palette.fg, palette.black).paint(separator),
-                    )
-                } else {
-                    (
-                        style!(palette.black, palette.green).paint(separator),
-                        style!(palette.black, palette.green)
-                            .bold()
-                            .paint(&swap_layout_name),
-                        style!(palette.green, palette.black).paint(separator),
-                    )
-                };
-            let swap_layout_indicator = format!(
-                "{}{}{}",
-                prefix_separator, swap_layout_name, suffix_separator
-            );
-            let (part, full_len) = (swap_layout_indicator.to_string(), swap_layout_name_len);
-            let short_len = swap_layout_name_len + 1; // 1 is the space between
-            if full_len <= max_len {
-                Some(LinePart {
-                    part,
-                    len: full_len,
-                    tab_index: None,
-                })
-            } else if short_len <= max_len && input_mode != InputMode::Locked {
-                Some(LinePart {
-                    part: swap_layout_indicator,
-                    len: short_len,
-                    tab_index: None,
-                })
-            } else {
-                None
+    let swap_layout_name = swap_layout_name.as_ref()?;
+    let mut swap_layout_name = format!(" {} ", swap_layout_name);
+    swap_layout_name.make_ascii_uppercase();
+
+    let (block_color, name_style) = if input_mode == InputMode::Locked {
+        (palette.fg, style(palette.black, Some(palette.fg)).italic())
+    } else if is_swap_layout_damaged {
+        (palette.fg, style(palette.black, Some(palette.fg)).bold())
+    } else {
+        (
+            palette.green,
+            style(palette.black, Some(palette.green)).bold(),
+        )
+    };
+    let mut swap_layout_indicator = StyledText::default();
+    swap_layout_indicator
+        .push_left_separator(separator, block_color, background)
+        .push(name_style, &swap_layout_name)
+        .push_right_separator(separator, block_color, background);
+    let swap_layout_indicator = swap_layout_indicator.into_line_part();
+    (swap_layout_indicator.len <= max_len).then_some(swap_layout_indicator)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::styled::tests::{visible_width, WIDE_NAMES};
+    use crate::tab::{tab_style, TabCache, TabIcons};
+
+    #[test]
+    fn swap_layout_status_len_is_width_of_wide_names() {
+        let palette = Palette::default();
+        for name in WIDE_NAMES {
+            for mode in [InputMode::Normal, InputMode::Locked] {
+                let part = swap_layout_status(
+                    usize::MAX,
+                    &Some(name.to_string()),
+                    false,
+                    mode,
+                    &palette,
+                    Some(palette.black),
+                    ARROW_SEPARATOR,
+                )
+                .unwrap();
+                assert_eq!(part.len, visible_width(&part.part), "{}", name);
             }
         }
-        None => None,
+    }
+
+    fn segment(text: &str) -> LinePart {
+        LinePart {
+            part: text.to_string(),
+            len: text.width(),
+            tab_index: None,
+            action: None,
+        }
+    }
+
+    #[test]
+    fn push_status_keeps_last_segment_first() {
+        let palette = Palette::default();
+        let push = |cols| {
+            let mut line = vec![];
+            push_status(
+                &mut line,
+                0,
+                cols,
+                TabAlignment::Left,
+                vec![segment("git"), segment("hints"), segment("12:00")],
+                &None,
+                false,
+                InputMode::Normal,
+                palette,
+                None,
+                PluginCapabilities::default(),
+            );
+            line.into_iter()
+                .map(|part| part.part)
+                .filter(|part| !part.trim().is_empty())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(push(13), ["git", "hints", "12:00"]);
+        // the segments after the first one that doesn't fit are dropped
+        assert_eq!(push(12), ["git", "12:00"]);
+        assert_eq!(push(7), ["12:00"]);
+        assert_eq!(push(4), ["git"]);
+    }
+
+    const BENCH_TABS: usize = 500;
+    const BENCH_RUNS: u32 = 200;
+
+    fn bench(name: &str, mut run: impl FnMut()) -> Duration {
+        let start = Instant::now();
+        for _ in 0..BENCH_RUNS {
+            run();
+        }
+        let elapsed = start.elapsed() / BENCH_RUNS;
+        println!("{}: {:?} per render", name, elapsed);
+        elapsed
+    }
+
+    // Run with `cargo test --release --target <host triple> -- --ignored --nocapture`, the
+    // default target is wasm.
+    #[test]
+    #[ignore = "timing, run manually"]
+    fn bench_tab_lines_with_many_tabs() {
+        let palette = Palette::default();
+        let capabilities = PluginCapabilities::default();
+        let icons = TabIcons {
+            fullscreen: "⛶",
+            sync: "⇄",
+        };
+        let active_tab_index = BENCH_TABS / 2;
+        let tabs: Vec<TabInfo> = (0..BENCH_TABS)
+            .map(|position| TabInfo {
+                position,
+                name: format!("tab {}", position),
+                active: position == active_tab_index,
+                ..Default::default()
+            })
+            .collect();
+        let layout = |render_tabs: &mut dyn FnMut(TabCompression) -> Vec<LinePart>, cols: usize| {
+            tab_lines(
+                Some("session"),
+                render_tabs,
+                vec![],
+                vec![],
+                TabAlignment::Left,
+                active_tab_index,
+                1,
+                cols,
+                palette,
+                Some(palette.black),
+                capabilities,
+                false,
+                InputMode::Normal,
+                &Some("default".to_string()),
+                false,
+            )
+        };
+
+        // narrow bar collapsing most tabs, and a wide one fitting most of them
+        for cols in [200, 5000] {
+            println!("{} tabs, {} columns", BENCH_TABS, cols);
+            let mut render_uncached = |compression| {
+                tabs.iter()
+                    .map(|tab| {
+                        tab_style(
+                            tab.name.clone(),
+                            tab,
+                            palette,
+                            Some(palette.black),
+                            capabilities,
+                            TabIndicators::default(),
+                            compression,
+                            icons,
+                            None,
+                        )
+                    })
+                    .collect()
+            };
+            let mut tab_cache = TabCache::default();
+            let mut render_cached = |compression| {
+                tabs.iter()
+                    .map(|tab| {
+                        tab_cache.tab_style(
+                            &tab.name,
+                            tab,
+                            palette,
+                            Some(palette.black),
+                            capabilities,
+                            TabIndicators::default(),
+                            compression,
+                            icons,
+                            None,
+                        )
+                    })
+                    .collect()
+            };
+            let expected = layout(&mut render_uncached, cols);
+            assert!(layout(&mut render_cached, cols) == expected);
+
+            let uncached = bench("  tabs rendered every time", || {
+                layout(&mut render_uncached, cols);
+            });
+            let cached = bench("  cached tabs", || {
+                layout(&mut render_cached, cols);
+            });
+            let layout_key = || LayoutKey {
+                tabs: tabs
+                    .iter()
+                    .map(|tab| (tab.clone(), tab.name.clone(), TabIndicators::default()))
+                    .collect(),
+                session_name: Some("session".to_string()),
+                left_segments: vec![],
+                segments: vec![],
+                alignment: TabAlignment::Left,
+                active_tab_index,
+                rows: 1,
+                cols,
+                palette,
+                background: Some(palette.black),
+                capabilities,
+                hide_session_name: false,
+                mode: InputMode::Normal,
+                swap_layout_name: Some("default".to_string()),
+                is_swap_layout_dirty: false,
+            };
+            let previous_key = layout_key();
+            let unchanged = bench("  unchanged layout", || {
+                assert!(layout_key() == previous_key);
+            });
+            println!(
+                "  {:.1}x faster with cached tabs, {:.1}x when the layout is unchanged",
+                uncached.as_secs_f64() / cached.as_secs_f64(),
+                uncached.as_secs_f64() / unchanged.as_secs_f64(),
+            );
+        }
     }
 }
@@ -1,72 +1,577 @@
+mod alert;
+mod ansi;
+mod cli;
+mod clock;
+mod color;
+mod command;
+mod config;
+mod git;
+mod hook;
 mod line;
+mod pushed;
+mod rule;
+mod segment;
+mod styled;
+mod sync;
 mod tab;
 
 use std::cmp::{max, min};
 use std::collections::BTreeMap;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 
-use tab::get_tab_to_focus;
+use tab::{get_clicked_line_part, get_tab_to_focus};
 use zellij_tile::prelude::*;
 
-use crate::line::tab_line;
-use crate::tab::tab_style;
+use crate::alert::{AlertClear, AlertSync, TabAlert, TabAlerts, ALERT_SYNC_VERSION};
+use crate::cli::{optional_arg, reply_line, required_arg, CliReply, CliResult};
+use crate::clock::format_time;
+use crate::color::downgrade_colors;
+use crate::command::{CommandSegment, COMMAND_SEGMENT_SOURCE};
+use crate::config::{Config, SegmentZone, SessionOverview};
+use crate::git::{pane_cwd, parse_git_status, run_git_status, GitStatus, GIT_STATUS_SOURCE};
+use crate::hook::{report_alert_hook_result, run_alert_hook, AlertHookEvent, ALERT_HOOK_SOURCE};
+use crate::line::{tab_lines, LayoutKey};
+use crate::pushed::{PushedSegment, PushedSegments, SegmentSync, SEGMENT_SYNC_VERSION};
+use crate::rule::{RuleColor, TabRule};
+use crate::segment::{
+    clock, command_segment, focused_pane_title, git_branch, is_focused, keybind_hints, pane_list,
+    pushed_segment, session_overview, ClickAction,
+};
+use crate::styled::bar_background;
+use crate::sync::{now_millis, read_payload, to_payload};
+use crate::tab::{format_duration, PaneCounts, TabCache, TabCompression, TabIcons, TabIndicators};
 
-use serde::{Deserialize, Serialize};
-
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct LinePart {
     part: String,
     len: usize,
     tab_index: Option<usize>,
+    action: Option<ClickAction>,
+}
+
+#[derive(Debug, Default)]
+struct SilenceWatch {
+    threshold: f64,
+    silent_for: f64,
+    title: String,
+    alerted: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
-struct TabAlert {
-    success: bool,
-    alternate_color: bool,
+#[derive(Debug)]
+struct Job {
+    pane_id: u32,
+    /// Milliseconds since the unix epoch.
+    started_at: u64,
 }
 
 #[derive(Default)]
 struct State {
+    config: Config,
+    plugin_id: u32,
     pane_info: PaneManifest,
-    tab_alerts: HashMap<usize, TabAlert>,
+    tab_alerts: TabAlerts,
+    tab_activity: HashSet<usize>,
+    tab_silence: HashSet<usize>,
+    silence_watches: HashMap<u32, SilenceWatch>,
+    jobs: HashMap<String, Job>,
+    timer_running: bool,
     tabs: Vec<TabInfo>,
     active_tab_idx: usize,
     mode_info: ModeInfo,
-    tab_line: Vec<LinePart>,
+    tab_lines: Vec<Vec<LinePart>>,
+    tab_cache: TabCache,
+    // inputs of the layout in `tab_lines`
+    layout_key: Option<LayoutKey>,
+    /// Text of the clock segment as of the last `Timer` event.
+    clock: String,
+    /// Names of the other live sessions.
+    live_sessions: Vec<String>,
+    resurrectable_sessions: usize,
+    /// Working directories reported by the shells of terminal panes.
+    pane_cwds: HashMap<u32, String>,
+    /// Directory the git segment is showing.
+    git_cwd: Option<String>,
+    /// Last git status of each directory, `None` for directories outside of a git repository.
+    git_statuses: HashMap<String, Option<GitStatus>>,
+    /// Seconds since the git status of `git_cwd` was last refreshed.
+    git_refresh_elapsed: f64,
+    pushed_segments: PushedSegments,
+    /// State of each command segment by id.
+    command_segments: HashMap<String, CommandSegment>,
 }
 
 static ARROW_SEPARATOR: &str = "";
 
 register_plugin!(State);
 
+// Provided by zellij to plugins, stubbed so the unit tests link outside of it.
+#[cfg(test)]
+#[no_mangle]
+extern "C" fn host_run_plugin_command() {}
+
+// Default message passed to the alert hook.
+fn alert_message(exit_code: i32, duration: Option<u64>) -> String {
+    let mut message = if exit_code == 0 {
+        "Command succeeded".to_string()
+    } else {
+        format!("Command failed with exit code {}", exit_code)
+    };
+    if let Some(duration) = duration {
+        message.push_str(&format!(" after {}", format_duration(duration)));
+    }
+    message
+}
+
+fn tab_with_terminal_pane(pane_info: &PaneManifest, pane_id: u32) -> Option<usize> {
+    pane_info.panes.iter().find_map(|(tab_idx, pane_vec)| {
+        pane_vec
+            .iter()
+            .any(|p| !p.is_plugin && p.id == pane_id)
+            .then_some(*tab_idx)
+    })
+}
+
+impl State {
+    // Marks background tabs with monitoring enabled whose panes changed their title since the
+    // last `PaneUpdate`, returns `true` if a tab got newly marked.
+    fn track_activity(&mut self, pane_info: &PaneManifest) -> bool {
+        let mut new_activity = false;
+        for (tab_idx, pane_vec) in &pane_info.panes {
+            // skip panes in current tab
+            if *tab_idx + 1 == self.active_tab_idx || self.tab_activity.contains(tab_idx) {
+                continue;
+            }
+            let monitored = self
+                .tabs
+                .get(*tab_idx)
+                .is_some_and(|t| self.config.monitor_activity.matches(&t.name));
+            if !monitored {
+                continue;
+            }
+
+            let title_changed = pane_vec.iter().filter(|p| !p.is_plugin).any(|pane| {
+                self.pane_info
+                    .panes
+                    .values()
+                    .flatten()
+                    .find(|p| !p.is_plugin && p.id == pane.id)
+                    .is_some_and(|previous| previous.title != pane.title)
+            });
+            if title_changed {
+                self.tab_activity.insert(*tab_idx);
+                new_activity = true;
+            }
+        }
+        new_activity
+    }
+
+    // Restarts the silence countdown of watched panes whose title changed and stops watching the
+    // ones that were closed.
+    fn reset_silence_watches(&mut self, pane_info: &PaneManifest) {
+        self.silence_watches.retain(|pane_id, watch| {
+            match pane_info
+                .panes
+                .values()
+                .flatten()
+                .find(|p| !p.is_plugin && p.id == *pane_id)
+            {
+                Some(pane) => {
+                    if pane.title != watch.title {
+                        watch.title = pane.title.clone();
+                        watch.silent_for = 0.0;
+                        watch.alerted = false;
+                    }
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+
+    // Advances the silence countdown of watched panes and marks the background tabs containing
+    // the ones that went quiet for longer than their threshold, returns `true` if a tab got
+    // newly marked.
+    fn track_silence(&mut self, elapsed: f64) -> bool {
+        let mut new_silence = false;
+        for (pane_id, watch) in self.silence_watches.iter_mut() {
+            watch.silent_for += elapsed;
+            if watch.alerted || watch.silent_for < watch.threshold {
+                continue;
+            }
+
+            if let Some(tab_idx) = tab_with_terminal_pane(&self.pane_info, *pane_id) {
+                // panes in the current tab are marked once the user leaves it
+                if tab_idx + 1 != self.active_tab_idx {
+                    watch.alerted = true;
+                    new_silence |= self.tab_silence.insert(tab_idx);
+                }
+            }
+        }
+        new_silence
+    }
+
+    // Turns silence monitoring on/off for a terminal pane, returns `false` if the pane doesn't
+    // exist.
+    fn toggle_silence_watch(&mut self, pane_id: u32, enabled: bool, threshold: f64) -> bool {
+        if !enabled {
+            self.silence_watches.remove(&pane_id);
+            return true;
+        }
+        let Some(pane) = self
+            .pane_info
+            .panes
+            .values()
+            .flatten()
+            .find(|p| !p.is_plugin && p.id == pane_id)
+        else {
+            return false;
+        };
+        self.silence_watches.insert(
+            pane_id,
+            SilenceWatch {
+                threshold,
+                silent_for: 0.0,
+                title: pane.title.clone(),
+                alerted: false,
+            },
+        );
+        self.start_timer();
+        true
+    }
+
+    fn cli_job_start(&mut self, args: &BTreeMap<String, String>) -> CliResult {
+        let job_id: String = required_arg(args, "job_id", "a string")?;
+        let pane_id: u32 = required_arg(args, "pane_id", "an integer")?;
+        let tab_idx = tab_with_terminal_pane(&self.pane_info, pane_id)
+            .ok_or_else(|| format!("unknown pane {}", pane_id))?;
+
+        let reply = CliReply::new(format!("job {} started on tab {}", job_id, tab_idx + 1));
+        self.jobs.insert(
+            job_id,
+            Job {
+                pane_id,
+                started_at: now_millis(),
+            },
+        );
+        // re-render every second to update the elapsed time
+        self.start_timer();
+        Ok(reply.render())
+    }
+
+    fn cli_tab_alert(&mut self, args: &BTreeMap<String, String>) -> CliResult {
+        let job_id: Option<String> = optional_arg(args, "job_id", "a string")?;
+        let pane_id: Option<u32> = optional_arg(args, "pane_id", "an integer")?;
+        let exit_code: i32 = required_arg(args, "exit_code", "an integer")?;
+        let message: Option<String> = optional_arg(args, "message", "a string")?;
+
+        // the job (if any) ends here, even if no alert ends up being raised
+        let job = job_id
+            .map(|job_id| {
+                self.jobs
+                    .remove(&job_id)
+                    .ok_or_else(|| format!("unknown job {}", job_id))
+            })
+            .transpose()?;
+        let pane_id = match (pane_id, &job) {
+            (Some(pane_id), _) => pane_id,
+            (None, Some(job)) => job.pane_id,
+            (None, None) => return Err("missing pane_id argument".to_string()),
+        };
+        let duration = job.map(|job| now_millis().saturating_sub(job.started_at));
+        let tab_idx = tab_with_terminal_pane(&self.pane_info, pane_id)
+            .ok_or_else(|| format!("unknown pane {}", pane_id))?;
+
+        // skip panes in current tab
+        if tab_idx + 1 == self.active_tab_idx {
+            return Ok(CliReply::new(format!(
+                "pane {} is in the active tab, no alert raised",
+                pane_id
+            ))
+            .render());
+        }
+
+        if let Some(duration) = duration {
+            if (duration as f64) < self.config.alert_min_duration * 1000.0 {
+                return Ok(CliReply::new(format!(
+                    "job finished in {}, below the alert threshold",
+                    format_duration(duration)
+                ))
+                .render());
+            }
+        }
+
+        if let Some(hook) = &self.config.alert_hook {
+            if self.is_primary_instance() {
+                let tab_name = self.tabs.get(tab_idx).map_or("", |t| t.name.as_str());
+                let message = match message {
+                    Some(message) => message,
+                    None => alert_message(exit_code, duration),
+                };
+                run_alert_hook(
+                    hook,
+                    AlertHookEvent {
+                        tab_name,
+                        pane_id,
+                        exit_code,
+                        message: &message,
+                    },
+                );
+            }
+        }
+
+        let first_alert = self.tab_alerts.is_empty();
+        self.tab_alerts
+            .raise(tab_idx, TabAlert::new(pane_id, exit_code == 0, duration));
+        let reply = CliReply::new(format!("alert raised on tab {}", tab_idx + 1));
+
+        // Only fire timer/re-render on the first alert (or when the elapsed time of a job has to
+        // go away), when the 1st timer expires the state is updated there and new timer is set.
+        if first_alert || duration.is_some() {
+            self.start_timer();
+            return Ok(reply.render());
+        }
+        Ok(reply)
+    }
+
+    fn cli_monitor_silence(&mut self, args: &BTreeMap<String, String>) -> CliResult {
+        let pane_id: u32 = required_arg(args, "pane_id", "an integer")?;
+        let enabled = optional_arg(args, "enabled", "true or false")?.unwrap_or(true);
+        let threshold: f64 =
+            optional_arg(args, "seconds", "a number")?.unwrap_or(self.config.silence_threshold);
+        if !(threshold > 0.0 && threshold.is_finite()) {
+            return Err("seconds must be a positive number".to_string());
+        }
+
+        if !self.toggle_silence_watch(pane_id, enabled, threshold) {
+            return Err(format!("unknown pane {}", pane_id));
+        }
+        Ok(CliReply::new(if enabled {
+            format!("watching pane {} for {}s of silence", pane_id, threshold)
+        } else {
+            format!("stopped watching pane {}", pane_id)
+        }))
+    }
+
+    fn cli_pane_cwd(&mut self, args: &BTreeMap<String, String>) -> CliResult {
+        let pane_id: u32 = required_arg(args, "pane_id", "an integer")?;
+        let cwd: String = required_arg(args, "cwd", "a string")?;
+        if tab_with_terminal_pane(&self.pane_info, pane_id).is_none() {
+            return Err(format!("unknown pane {}", pane_id));
+        }
+
+        let reply = CliReply::new(format!("pane {} is in {}", pane_id, cwd));
+        self.pane_cwds.insert(pane_id, cwd);
+        if self.refresh_git_status(false) {
+            return Ok(reply.render());
+        }
+        Ok(reply)
+    }
+
+    fn cli_segment(&mut self, args: &BTreeMap<String, String>, payload: Option<&str>) -> CliResult {
+        let id: String = required_arg(args, "id", "a string")?;
+        let ttl: Option<f64> = optional_arg(args, "ttl", "a number")?;
+        let color: Option<String> = optional_arg(args, "color", "a string")?;
+        let click: Option<String> = optional_arg(args, "click", "a string")?;
+        // control characters (e.g. escape sequences) would break the layout of the bar
+        let text: String = payload
+            .unwrap_or_default()
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect();
+        let text = text.trim();
+
+        let now = now_millis();
+        // an empty payload removes the segment
+        if text.is_empty() {
+            if !self.pushed_segments.remove(&id, now) {
+                return Err(format!("unknown segment {}", id));
+            }
+            return Ok(CliReply::new(format!("segment {} removed", id)).render());
+        }
+        if ttl.is_some_and(|ttl| !(ttl > 0.0 && ttl.is_finite())) {
+            return Err("ttl must be a positive number".to_string());
+        }
+        let color = color
+            .map(|color| RuleColor::parse(&color).ok_or_else(|| format!("unknown color {}", color)))
+            .transpose()?;
+        if let Some(click) = &click {
+            if ClickAction::parse(click).is_none() {
+                return Err(format!("invalid click action {}", click));
+            }
+        }
+
+        let reply = CliReply::new(format!("segment {} set", id));
+        self.pushed_segments.set(
+            id,
+            PushedSegment {
+                text: text.to_string(),
+                color,
+                click,
+                updated_at: now,
+                // huge ttls saturate, keeping the segment until it's removed
+                expires_at: ttl.map(|ttl| now.saturating_add((ttl * 1000.0) as u64)),
+            },
+        );
+        // expire segments and broadcast them to other instances on every tick
+        self.start_timer();
+        Ok(reply.render())
+    }
+
+    // Working directory of the focused pane of the active tab, as reported by its shell or guessed
+    // from its title.
+    fn focused_pane_cwd(&self) -> Option<String> {
+        let active_tab_index = self.active_tab_idx.checked_sub(1)?;
+        let floating_panes_visible = self.tabs.get(active_tab_index)?.are_floating_panes_visible;
+        let pane = self
+            .pane_info
+            .panes
+            .get(&active_tab_index)?
+            .iter()
+            .find(|p| !p.is_plugin && !p.is_suppressed && is_focused(p, floating_panes_visible))?;
+        pane_cwd(&self.pane_cwds, pane).map(String::from)
+    }
+
+    // Runs `git status` when the directory of the focused pane changed (or every time if `force`
+    // is set), returns `true` if the segment has to be re-rendered. Only the instance in the
+    // active tab does it as the other ones aren't visible, they catch up when their tab gets
+    // focused.
+    fn refresh_git_status(&mut self, force: bool) -> bool {
+        if !self.config.git_status || !self.is_in_active_tab() {
+            return false;
+        }
+        let cwd = self.focused_pane_cwd();
+        let cwd_changed = cwd != self.git_cwd;
+        if !cwd_changed && !force {
+            return false;
+        }
+        if let Some(cwd) = &cwd {
+            run_git_status(cwd);
+        }
+        self.git_cwd = cwd;
+        self.git_refresh_elapsed = 0.0;
+        cwd_changed
+    }
+
+    fn is_in_active_tab(&self) -> bool {
+        let own_tab = self.pane_info.panes.iter().find_map(|(tab_idx, pane_vec)| {
+            pane_vec
+                .iter()
+                .any(|p| p.is_plugin && p.id == self.plugin_id)
+                .then_some(*tab_idx)
+        });
+        match own_tab {
+            Some(tab_idx) => tab_idx + 1 == self.active_tab_idx,
+            None => true,
+        }
+    }
+
+    // CLI pipes are delivered to every instance of the plugin, only the one with the lowest id
+    // answers/runs hooks so the command doesn't print the same reply (or run the same hook) once
+    // per tab.
+    fn is_primary_instance(&self) -> bool {
+        let plugin_panes = self
+            .pane_info
+            .panes
+            .values()
+            .flatten()
+            .filter(|p| p.is_plugin);
+        let own_url = plugin_panes
+            .clone()
+            .find(|p| p.id == self.plugin_id)
+            .and_then(|p| p.plugin_url.as_ref());
+        match own_url {
+            Some(own_url) => plugin_panes
+                .filter(|p| p.plugin_url.as_ref() == Some(own_url))
+                .all(|p| p.id >= self.plugin_id),
+            None => true,
+        }
+    }
+
+    // Formats the current time, returns `true` if the text of the clock changed (e.g. only once a
+    // minute with the `%H:%M` format).
+    fn update_clock(&mut self) -> bool {
+        let Some(format) = &self.config.clock_format else {
+            return false;
+        };
+        let now = (now_millis() / 1000) as i64;
+        let clock = format_time(format, now, self.config.clock_utc_offset);
+        if clock == self.clock {
+            return false;
+        }
+        self.clock = clock;
+        true
+    }
+
+    // Alerts, silence monitoring and the clock share a single 1s timer loop, this makes sure only one
+    // `set_timeout` is pending at any time.
+    fn start_timer(&mut self) {
+        if !self.timer_running {
+            set_timeout(1.0);
+            self.timer_running = true;
+        }
+    }
+}
+
 impl ZellijPlugin for State {
-    fn load(&mut self, _configuration: BTreeMap<String, String>) {
-        request_permission(&[
+    fn load(&mut self, configuration: BTreeMap<String, String>) {
+        self.config = Config::new(&configuration);
+        self.plugin_id = get_plugin_ids().plugin_id;
+        let mut permissions = vec![
             PermissionType::ReadApplicationState,
             PermissionType::ChangeApplicationState,
             PermissionType::MessageAndLaunchOtherPlugins,
-        ]);
-        subscribe(&[
+            PermissionType::ReadCliPipes,
+        ];
+        // only ask for it when needed, running commands is a big ask for a status bar
+        if self.config.alert_hook.is_some()
+            || self.config.git_status
+            || !self.config.command_segments.is_empty()
+        {
+            permissions.push(PermissionType::RunCommands);
+        }
+        request_permission(&permissions);
+        let mut events = vec![
             EventType::TabUpdate,
             EventType::PaneUpdate,
             EventType::ModeUpdate,
             EventType::Mouse,
             EventType::PermissionRequestResult,
             EventType::Timer,
-        ]);
+            EventType::RunCommandResult,
+        ];
+        if self.config.session_overview != SessionOverview::Hidden {
+            events.push(EventType::SessionUpdate);
+        }
+        subscribe(&events);
         // Set as selectable on load so user can accept/deny perms.
         // After the first load, if the user allowed access, the perm event handler
         // in `update` will always set it as unselectable.
         set_selectable(true);
+        if self.config.clock_format.is_some() {
+            self.update_clock();
+            self.start_timer();
+        }
+        if self.config.git_status || !self.config.command_segments.is_empty() {
+            self.start_timer();
+        }
     }
 
     fn update(&mut self, event: Event) -> bool {
         let mut should_render = false;
         match event {
             Event::PaneUpdate(pane_info) => {
+                should_render = self.track_activity(&pane_info);
+                self.reset_silence_watches(&pane_info);
+                self.pane_cwds
+                    .retain(|pane_id, _| tab_with_terminal_pane(&pane_info, *pane_id).is_some());
+                // the alert of jobs whose pane closed never arrives
+                let job_count = self.jobs.len();
+                self.jobs
+                    .retain(|_, job| tab_with_terminal_pane(&pane_info, job.pane_id).is_some());
+                should_render |= job_count != self.jobs.len();
                 self.pane_info = pane_info;
+                should_render |= self.refresh_git_status(false);
             }
             Event::ModeUpdate(mode_info) => {
                 if self.mode_info != mode_info {
@@ -74,19 +579,66 @@ impl ZellijPlugin for State {
                 }
                 self.mode_info = mode_info
             }
-            Event::Timer(_) => {
+            Event::Timer(elapsed) => {
+                self.timer_running = false;
+
+                if !self.silence_watches.is_empty() {
+                    should_render = self.track_silence(elapsed);
+                    self.start_timer();
+                }
+
+                if self.config.clock_format.is_some() {
+                    should_render |= self.update_clock();
+                    self.start_timer();
+                }
+
+                if self.config.git_status {
+                    self.git_refresh_elapsed += elapsed;
+                    if self.git_refresh_elapsed >= self.config.git_refresh_interval {
+                        should_render |= self.refresh_git_status(true);
+                    }
+                    self.start_timer();
+                }
+
+                if !self.config.command_segments.is_empty() {
+                    // like the git segment, only the visible instance runs the commands
+                    let can_run = self.is_in_active_tab();
+                    for config in &self.config.command_segments {
+                        should_render |= self
+                            .command_segments
+                            .entry(config.id.clone())
+                            .or_default()
+                            .tick(config, elapsed, can_run);
+                    }
+                    self.start_timer();
+                }
+
+                if !self.pushed_segments.is_empty() {
+                    should_render |= self.pushed_segments.expire(now_millis());
+                    self.start_timer();
+
+                    // same as alerts, lets instances created after the segments were pushed catch
+                    // up
+                    pipe_message_to_plugin(
+                        MessageToPlugin::new("zj-status-bar:plugin:segment:broadcast")
+                            .with_plugin_url("zellij:OWN_URL")
+                            .with_payload(to_payload(&self.pushed_segments.sync_message())),
+                    )
+                }
+
+                // keep the elapsed time of running jobs up to date
+                if !self.jobs.is_empty() {
+                    should_render = true;
+                    self.start_timer();
+                }
+
                 // Skip event if there's no alerts.
                 // This ensures the last timer fired after visited the last tab with an alert don't
                 // cause an infinite re-render loop.
                 if !self.tab_alerts.is_empty() {
-                    for tab_alert in self.tab_alerts.values_mut() {
-                        *tab_alert = TabAlert {
-                            success: tab_alert.success,
-                            alternate_color: !tab_alert.alternate_color,
-                        }
-                    }
+                    self.tab_alerts.toggle_blink();
 
-                    set_timeout(1.0);
+                    self.start_timer();
                     should_render = true;
 
                     // Broadcast the state of tab alerts to all instances of `zj-status-bar` for new
@@ -94,7 +646,7 @@ impl ZellijPlugin for State {
                     pipe_message_to_plugin(
                         MessageToPlugin::new("zj-status-bar:plugin:tab_alert:broadcast")
                             .with_plugin_url("zellij:OWN_URL")
-                            .with_payload(serde_json::to_string(&self.tab_alerts).unwrap()),
+                            .with_payload(to_payload(&self.tab_alerts.sync_message())),
                     )
                 }
             }
@@ -103,20 +655,39 @@ impl ZellijPlugin for State {
                     // tabs are indexed starting from 1 so we need to add 1
                     let active_tab_idx = active_tab_index + 1;
                     if self.active_tab_idx != active_tab_idx || self.tabs != tabs {
-                        self.tab_alerts.remove(&active_tab_index);
+                        // Let other instances know the alert was acknowledged so they stop
+                        // broadcasting it.
+                        if let Some(clear) = self.tab_alerts.clear(active_tab_index, now_millis()) {
+                            pipe_message_to_plugin(
+                                MessageToPlugin::new("zj-status-bar:plugin:tab_alert:clear")
+                                    .with_plugin_url("zellij:OWN_URL")
+                                    .with_payload(to_payload(&clear)),
+                            )
+                        }
+                        self.tab_activity.remove(&active_tab_index);
+                        self.tab_silence.remove(&active_tab_index);
                         should_render = true;
                     }
                     self.active_tab_idx = active_tab_idx;
                     self.tabs = tabs;
+                    should_render |= self.refresh_git_status(false);
                 } else {
                     eprintln!("Could not find active tab.");
                 }
             }
             Event::Mouse(me) => match me {
-                Mouse::LeftClick(_, col) => {
-                    let tab_to_focus = get_tab_to_focus(&self.tab_line, self.active_tab_idx, col);
-                    if let Some(idx) = tab_to_focus {
-                        switch_tab_to(idx.try_into().unwrap());
+                Mouse::LeftClick(line, col) => {
+                    let tab_line = usize::try_from(line)
+                        .ok()
+                        .and_then(|line| self.tab_lines.get(line));
+                    if let Some(tab_line) = tab_line {
+                        if let Some(idx) = get_tab_to_focus(tab_line, self.active_tab_idx, col) {
+                            switch_tab_to(idx.try_into().unwrap());
+                        } else if let Some(action) = get_clicked_line_part(tab_line, col)
+                            .and_then(|part| part.action.as_ref())
+                        {
+                            action.run();
+                        }
                     }
                 }
                 Mouse::ScrollUp(_) => {
@@ -127,6 +698,46 @@ impl ZellijPlugin for State {
                 }
                 _ => {}
             },
+            Event::RunCommandResult(exit_code, stdout, stderr, context) => {
+                match context.get("source").map(String::as_str) {
+                    Some(ALERT_HOOK_SOURCE) => {
+                        report_alert_hook_result(exit_code, &stderr, &context)
+                    }
+                    Some(COMMAND_SEGMENT_SOURCE) => {
+                        let segment = context
+                            .get("id")
+                            .and_then(|id| self.command_segments.get_mut(id));
+                        let run_id = context.get("run").and_then(|run| run.parse().ok());
+                        if let (Some(segment), Some(run_id)) = (segment, run_id) {
+                            should_render = segment.handle_result(run_id, exit_code, &stdout);
+                        }
+                    }
+                    Some(GIT_STATUS_SOURCE) => {
+                        if let Some(cwd) = context.get("cwd") {
+                            let status = parse_git_status(exit_code, &stdout);
+                            let previous = self.git_statuses.insert(cwd.clone(), status.clone());
+                            should_render =
+                                self.git_cwd.as_ref() == Some(cwd) && previous != Some(status);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::SessionUpdate(sessions, resurrectable_sessions) => {
+                let live_sessions: Vec<String> = sessions
+                    .into_iter()
+                    .filter(|s| !s.is_current_session)
+                    .map(|s| s.name)
+                    .collect();
+                // sent periodically, only re-render when sessions come and go
+                if self.live_sessions != live_sessions
+                    || self.resurrectable_sessions != resurrectable_sessions.len()
+                {
+                    self.live_sessions = live_sessions;
+                    self.resurrectable_sessions = resurrectable_sessions.len();
+                    should_render = true;
+                }
+            }
             Event::PermissionRequestResult(result) => match result {
                 PermissionStatus::Granted => set_selectable(false),
                 PermissionStatus::Denied => eprintln!("Permission denied by user."),
@@ -141,68 +752,67 @@ impl ZellijPlugin for State {
     fn pipe(&mut self, pipe_message: PipeMessage) -> bool {
         let mut should_render = false;
         match pipe_message.source {
-            PipeSource::Cli(_) => {
-                if pipe_message.name == "zj-status-bar:cli:tab_alert" {
-                    if let (Some(pane_id_str), Some(exit_code_str)) = (
-                        pipe_message.args.get("pane_id"),
-                        pipe_message.args.get("exit_code"),
-                    ) {
-                        let pane_id: u32 = match pane_id_str.parse() {
-                            Ok(int) => int,
-                            Err(..) => return false,
-                        };
-                        let exit_code: u32 = match exit_code_str.parse() {
-                            Ok(int) => int,
-                            Err(..) => return false,
-                        };
-
-                        for (tab_idx, pane_vec) in &self.pane_info.panes {
-                            // skip panes in current tab
-                            if *tab_idx == self.active_tab_idx - 1 {
-                                continue;
-                            }
-
-                            // find index of tab containing the pane
-                            if pane_vec.iter().any(|p| p.id == pane_id) {
-                                let first_alert = self.tab_alerts.is_empty();
-
-                                self.tab_alerts.insert(
-                                    *tab_idx,
-                                    TabAlert {
-                                        success: exit_code == 0,
-                                        alternate_color: true,
-                                    },
-                                );
-
-                                // Only fire timer/re-render on the first alert, when the 1st timer
-                                // expires the state is updated there and new timer is set.
-                                if first_alert {
-                                    set_timeout(1.0);
-                                    should_render = true;
-                                }
-
-                                // tab index found, exit loop
-                                break;
-                            }
-                        }
+            PipeSource::Cli(pipe_id) => {
+                let result = match pipe_message.name.as_str() {
+                    "zj-status-bar:cli:tab_alert" => self.cli_tab_alert(&pipe_message.args),
+                    "zj-status-bar:cli:job_start" => self.cli_job_start(&pipe_message.args),
+                    "zj-status-bar:cli:monitor_silence" => {
+                        self.cli_monitor_silence(&pipe_message.args)
+                    }
+                    "zj-status-bar:cli:pane_cwd" => self.cli_pane_cwd(&pipe_message.args),
+                    "zj-status-bar:cli:segment" => {
+                        self.cli_segment(&pipe_message.args, pipe_message.payload.as_deref())
                     }
+                    // not meant for this plugin
+                    _ => return false,
+                };
+                if self.is_primary_instance() {
+                    cli_pipe_output(&pipe_id, &reply_line(&result));
                 }
+                should_render = result.is_ok_and(|reply| reply.should_render);
             }
             PipeSource::Plugin(_source_plugin_id) => {
-                // This message is sent by other plugin instances on each `Timer` event and
-                // contains the state of tabs alerts.
-                //
-                // Only read it if the current instance doesn't contain any info (new tab created
-                // after alerts were piped from a pane) to "catch up" and render them.
-                if pipe_message.is_private
-                    && pipe_message.name == "zj-status-bar:plugin:tab_alert:broadcast"
-                    && self.tab_alerts.is_empty()
-                {
-                    self.tab_alerts = serde_json::from_str(&pipe_message.payload.unwrap()).unwrap();
+                let active_tab_index = self.active_tab_idx.saturating_sub(1);
+                let first_alert = self.tab_alerts.is_empty();
+                let first_segment = self.pushed_segments.is_empty();
+                if !pipe_message.is_private {
+                    return false;
+                }
 
-                    // fire 1st timer/re-render
-                    set_timeout(1.0);
-                    should_render = true;
+                if pipe_message.name == "zj-status-bar:plugin:tab_alert:broadcast" {
+                    // This message is sent by other plugin instances on each `Timer` event and
+                    // contains the state of tabs alerts.
+                    //
+                    // Merge it into the local state so new instances (tab created after alerts
+                    // were piped from a pane) "catch up" and existing ones pick up alerts they
+                    // missed.
+                    if let Some(sync) = read_payload::<AlertSync>(&pipe_message, ALERT_SYNC_VERSION)
+                    {
+                        should_render = self.tab_alerts.apply_sync(sync, active_tab_index);
+                    }
+                } else if pipe_message.name == "zj-status-bar:plugin:tab_alert:clear" {
+                    // Sent by the other instances when an alert gets cleared by focusing its tab.
+                    if let Some(clear) =
+                        read_payload::<AlertClear>(&pipe_message, ALERT_SYNC_VERSION)
+                    {
+                        should_render =
+                            self.tab_alerts.apply_clear(clear.tab_idx, clear.cleared_at);
+                    }
+                } else if pipe_message.name == "zj-status-bar:plugin:segment:broadcast" {
+                    // Sent by the other instances on each `Timer` event while there are pushed
+                    // segments.
+                    if let Some(sync) =
+                        read_payload::<SegmentSync>(&pipe_message, SEGMENT_SYNC_VERSION)
+                    {
+                        should_render = self.pushed_segments.apply_sync(sync);
+                    }
+                }
+
+                if first_alert && !self.tab_alerts.is_empty()
+                    || first_segment && !self.pushed_segments.is_empty()
+                {
+                    // fire 1st timer
+                    self.start_timer();
                 }
             }
             _ => {
@@ -212,15 +822,15 @@ impl ZellijPlugin for State {
         should_render
     }
 
-    fn render(&mut self, _rows: usize, cols: usize) {
+    fn render(&mut self, rows: usize, cols: usize) {
         if self.tabs.is_empty() {
             return;
         }
-        let mut all_tabs: Vec<LinePart> = vec![];
+        let mut tab_labels: Vec<(String, TabIndicators)> = vec![];
         let mut active_tab_index = 0;
         let mut active_swap_layout_name = None;
         let mut is_swap_layout_dirty = false;
-        for t in &mut self.tabs {
+        for t in &self.tabs {
             let mut tabname = t.name.clone();
             if t.active && self.mode_info.mode == InputMode::RenameTab {
                 if tabname.is_empty() {
@@ -233,54 +843,216 @@ impl ZellijPlugin for State {
                 active_swap_layout_name = t.active_swap_layout_name.clone();
             }
 
-            // insert tab index
-            tabname.insert_str(0, &format!("{} ", t.position + 1));
+            let mut indicators = TabIndicators {
+                activity: self.tab_activity.contains(&t.position),
+                silence: self.tab_silence.contains(&t.position),
+                ..Default::default()
+            };
 
-            let mut alternate_color = false;
-            let mut success = false;
+            if self.config.pane_counts {
+                indicators.pane_counts = self
+                    .pane_info
+                    .panes
+                    .get(&t.position)
+                    .map(|panes| PaneCounts::new(panes));
+            }
 
             if let Some(i) = self.tab_alerts.get(&t.position) {
-                alternate_color = i.alternate_color;
-                success = i.success;
+                indicators.alert = true;
+                indicators.alternate_color = i.alternate_color;
+                indicators.success = i.success;
+                indicators.duration = i.duration;
             }
 
-            let tab = tab_style(
-                tabname,
-                t,
-                self.mode_info.style.colors,
-                self.mode_info.capabilities,
-                alternate_color,
-                success,
-            );
-            all_tabs.push(tab);
+            // the longest running job in the tab takes precedence over the duration of the last
+            // alert
+            let now = now_millis();
+            let panes = self.pane_info.panes.get(&t.position);
+            if let Some(elapsed) = self
+                .jobs
+                .values()
+                .filter(|job| {
+                    panes.is_some_and(|p| p.iter().any(|p| !p.is_plugin && p.id == job.pane_id))
+                })
+                .map(|job| now.saturating_sub(job.started_at))
+                .max()
+            {
+                indicators.duration = Some(elapsed);
+            }
+
+            tab_labels.push((tabname, indicators));
         }
-        self.tab_line = tab_line(
-            self.mode_info.session_name.as_deref(),
-            all_tabs,
-            active_tab_index,
-            cols.saturating_sub(1),
+        let icons = TabIcons {
+            fullscreen: &self.config.fullscreen_icon,
+            sync: &self.config.sync_icon,
+        };
+        let background = bar_background(
             self.mode_info.style.colors,
+            self.config.transparent_background,
+        );
+        self.tab_cache.prune(
+            self.tabs.len(),
+            self.mode_info.style.colors,
+            background,
             self.mode_info.capabilities,
-            self.mode_info.style.hide_session_name,
-            self.mode_info.mode,
-            &active_swap_layout_name,
-            is_swap_layout_dirty,
         );
-        let output = self
-            .tab_line
-            .iter()
-            .fold(String::new(), |output, part| output + &part.part);
-        let background = match self.mode_info.style.colors.theme_hue {
-            ThemeHue::Dark => self.mode_info.style.colors.black,
-            ThemeHue::Light => self.mode_info.style.colors.white,
+        let render_tabs = |compression| {
+            self.tabs
+                .iter()
+                .zip(&tab_labels)
+                .map(|(t, (tabname, indicators))| {
+                    // keep showing the whole name while it's being edited
+                    let compression = if t.active && self.mode_info.mode == InputMode::RenameTab {
+                        TabCompression::Full
+                    } else {
+                        compression
+                    };
+                    self.tab_cache.tab_style(
+                        tabname,
+                        t,
+                        self.mode_info.style.colors,
+                        background,
+                        self.mode_info.capabilities,
+                        *indicators,
+                        compression,
+                        icons,
+                        TabRule::find(&self.config.tab_rules, &t.name),
+                    )
+                })
+                .collect()
         };
-        match background {
-            PaletteColor::Rgb((r, g, b)) => {
-                print!("{}\u{1b}[48;2;{};{};{}m\u{1b}[0K", output, r, g, b);
+        let mut segments = vec![];
+        if let (Some(tab), Some(panes)) = (
+            self.tabs.get(active_tab_index),
+            self.pane_info.panes.get(&active_tab_index),
+        ) {
+            if self.config.focused_pane {
+                segments.extend(focused_pane_title(
+                    panes,
+                    tab.are_floating_panes_visible,
+                    &self.config.focused_pane_format,
+                    self.config.focused_pane_max_width,
+                    &self.pane_cwds,
+                    self.mode_info.style.colors,
+                    background,
+                ));
             }
-            PaletteColor::EightBit(color) => {
-                print!("{}\u{1b}[48;5;{}m\u{1b}[0K", output, color);
+            if self.config.pane_list {
+                segments.extend(pane_list(
+                    panes,
+                    tab.are_floating_panes_visible,
+                    self.plugin_id,
+                    self.mode_info.style.colors,
+                    background,
+                ));
             }
         }
+        if self.config.keybind_hints {
+            segments.extend(keybind_hints(
+                &self.mode_info,
+                self.mode_info.style.colors,
+                background,
+            ));
+        }
+        if let Some(Some(status)) = self
+            .git_cwd
+            .as_ref()
+            .and_then(|cwd| self.git_statuses.get(cwd))
+        {
+            segments.push(git_branch(status, self.mode_info.style.colors, background));
+        }
+        segments.extend(session_overview(
+            &self.live_sessions,
+            self.resurrectable_sessions,
+            &self.config.session_overview,
+            self.mode_info.style.colors,
+            background,
+        ));
+        segments.extend(self.config.command_segments.iter().filter_map(|config| {
+            let segment = self.command_segments.get(&config.id)?;
+            command_segment(
+                &config.id,
+                &segment.output,
+                self.mode_info.style.colors,
+                background,
+            )
+        }));
+        let pushed_segments = self
+            .pushed_segments
+            .iter()
+            .map(|segment| pushed_segment(segment, self.mode_info.style.colors, background));
+        let mut left_segments = vec![];
+        match self.config.pushed_segments_zone {
+            SegmentZone::Left => left_segments.extend(pushed_segments),
+            SegmentZone::Right => segments.extend(pushed_segments),
+        }
+        // last so it ends up next to the swap layout status, at the far right
+        if self.config.clock_format.is_some() {
+            segments.push(clock(&self.clock, self.mode_info.style.colors, background));
+        }
+        let layout_key = LayoutKey {
+            tabs: self
+                .tabs
+                .iter()
+                .zip(&tab_labels)
+                .map(|(tab, (tabname, indicators))| (tab.clone(), tabname.clone(), *indicators))
+                .collect(),
+            session_name: self.mode_info.session_name.clone(),
+            left_segments: left_segments.clone(),
+            segments: segments.clone(),
+            alignment: self.config.tab_alignment,
+            active_tab_index,
+            rows,
+            cols,
+            palette: self.mode_info.style.colors,
+            background,
+            capabilities: self.mode_info.capabilities,
+            hide_session_name: self.mode_info.style.hide_session_name,
+            mode: self.mode_info.mode,
+            swap_layout_name: active_swap_layout_name.clone(),
+            is_swap_layout_dirty,
+        };
+        // e.g. renders triggered by events that don't change what the bar shows
+        if self.layout_key.as_ref() != Some(&layout_key) {
+            self.tab_lines = tab_lines(
+                self.mode_info.session_name.as_deref(),
+                render_tabs,
+                left_segments,
+                segments,
+                self.config.tab_alignment,
+                active_tab_index,
+                rows,
+                cols.saturating_sub(1),
+                self.mode_info.style.colors,
+                background,
+                self.mode_info.capabilities,
+                self.mode_info.style.hide_session_name,
+                self.mode_info.mode,
+                &active_swap_layout_name,
+                is_swap_layout_dirty,
+            );
+            self.layout_key = Some(layout_key);
+        }
+        // the rest of each row is cleared with the background of the bar, or the default one of
+        // the terminal in transparent mode
+        let line_end = match background {
+            Some(PaletteColor::Rgb((r, g, b))) => {
+                format!("\u{1b}[48;2;{};{};{}m\u{1b}[0K", r, g, b)
+            }
+            Some(PaletteColor::EightBit(color)) => format!("\u{1b}[48;5;{}m\u{1b}[0K", color),
+            None => "\u{1b}[0m\u{1b}[0K".to_string(),
+        };
+        // rows left over by the tabs are filled with the background
+        let output = (0..rows.max(1))
+            .map(|row| {
+                let line = self.tab_lines.get(row).map_or(String::new(), |line| {
+                    line.iter()
+                        .fold(String::new(), |output, part| output + &part.part)
+                });
+                line + &line_end
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        print!("{}", downgrade_colors(&output, self.config.color_depth));
     }
 }
@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rule::RuleColor;
+use crate::sync::{now_millis, Versioned, VersionedMap};
+
+/// Version of [`SegmentSync`], bump it on every incompatible change.
+pub const SEGMENT_SYNC_VERSION: u32 = 1;
+
+/// Text pushed by a script through the `zj-status-bar:cli:segment` pipe.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PushedSegment {
+    pub text: String,
+    pub color: Option<RuleColor>,
+    /// Click action spec, see `ClickAction::parse`.
+    pub click: Option<String>,
+    /// Milliseconds since the unix epoch.
+    pub updated_at: u64,
+    /// Milliseconds since the unix epoch, the segment is kept until removed when unset.
+    pub expires_at: Option<u64>,
+}
+
+impl Versioned for PushedSegment {
+    fn updated_at(&self) -> u64 {
+        self.updated_at
+    }
+}
+
+/// Pushed segments by id, removed segments reject older versions of themselves so stale segments
+/// broadcasted by other instances aren't brought back.
+#[derive(Debug, Default)]
+pub struct PushedSegments {
+    segments: VersionedMap<String, PushedSegment>,
+}
+
+impl PushedSegments {
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Segments ordered by id.
+    pub fn iter(&self) -> impl Iterator<Item = &PushedSegment> {
+        self.segments.entries().values()
+    }
+
+    pub fn set(&mut self, id: String, segment: PushedSegment) {
+        self.segments.insert(id, segment);
+    }
+
+    /// Returns `false` if there was no segment with that id.
+    pub fn remove(&mut self, id: &str, removed_at: u64) -> bool {
+        self.segments.remove(&id.to_string(), removed_at).is_some()
+    }
+
+    /// Drops the segments whose ttl ran out, returns `true` if any was dropped.
+    pub fn expire(&mut self, now: u64) -> bool {
+        let count = self.segments.entries().len();
+        self.segments
+            .retain(|_, segment| segment.expires_at.is_none_or(|at| at > now));
+        self.segments.prune_tombstones(now);
+        count != self.segments.entries().len()
+    }
+
+    pub fn sync_message(&self) -> SegmentSync {
+        SegmentSync {
+            version: SEGMENT_SYNC_VERSION,
+            sent_at: now_millis(),
+            segments: self.segments.entries().clone(),
+            tombstones: self.segments.tombstones().clone(),
+        }
+    }
+
+    /// Merges the state broadcasted by another instance, keeping the most recent version of each
+    /// segment.
+    ///
+    /// Returns `true` if any segment changed.
+    pub fn apply_sync(&mut self, sync: SegmentSync) -> bool {
+        let mut changed = self.segments.apply_sync(sync.segments, sync.tombstones);
+        changed |= self.expire(now_millis());
+        changed
+    }
+}
+
+/// Payload of the `zj-status-bar:plugin:segment:broadcast` message.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SegmentSync {
+    pub version: u32,
+    /// Milliseconds since the unix epoch.
+    pub sent_at: u64,
+    pub segments: BTreeMap<String, PushedSegment>,
+    pub tombstones: BTreeMap<String, u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(text: &str, updated_at: u64) -> PushedSegment {
+        PushedSegment {
+            text: text.to_string(),
+            color: None,
+            click: None,
+            updated_at,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn stale_broadcast_does_not_bring_back_removed_segment() {
+        let (mut first, mut second) = (PushedSegments::default(), PushedSegments::default());
+        let updated_at = now_millis();
+        first.set("deploy".to_string(), segment("deploying", updated_at));
+        assert!(second.apply_sync(first.sync_message()));
+        let stale = second.sync_message();
+
+        assert!(first.remove("deploy", updated_at + 1));
+        assert!(!first.apply_sync(stale));
+        assert!(first.is_empty());
+        assert!(second.apply_sync(first.sync_message()));
+        assert!(second.is_empty());
+
+        // pushed again after the removal
+        second.set("deploy".to_string(), segment("deployed", updated_at + 2));
+        assert!(first.apply_sync(second.sync_message()));
+        assert_eq!(first.iter().next().unwrap().text, "deployed");
+    }
+}
@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use zellij_tile::prelude::*;
+
+/// Color and/or icon applied to every tab whose name matches `pattern`.
+///
+/// Parsed from the `tab_rules` config option, a `;`-separated list of `pattern:color:icon` rules
+/// where color and icon are optional (e.g. `prod*:red:⚠;*-ssh:orange;db::⛁`).
+#[derive(Debug)]
+pub struct TabRule {
+    pattern: String,
+    pub color: Option<RuleColor>,
+    pub icon: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum RuleColor {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    Orange,
+    Gray,
+    Rgb(u8, u8, u8),
+}
+
+impl RuleColor {
+    pub fn parse(value: &str) -> Option<Self> {
+        let color = match value.to_lowercase().as_str() {
+            "red" => RuleColor::Red,
+            "green" => RuleColor::Green,
+            "yellow" => RuleColor::Yellow,
+            "blue" => RuleColor::Blue,
+            "magenta" => RuleColor::Magenta,
+            "cyan" => RuleColor::Cyan,
+            "orange" => RuleColor::Orange,
+            "gray" | "grey" => RuleColor::Gray,
+            hex => {
+                let hex = hex.strip_prefix('#')?;
+                if hex.len() != 6 {
+                    return None;
+                }
+                let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+                RuleColor::Rgb(channel(0)?, channel(2)?, channel(4)?)
+            }
+        };
+        Some(color)
+    }
+
+    pub fn to_palette_color(self, palette: Palette) -> PaletteColor {
+        match self {
+            RuleColor::Red => palette.red,
+            RuleColor::Green => palette.green,
+            RuleColor::Yellow => palette.yellow,
+            RuleColor::Blue => palette.blue,
+            RuleColor::Magenta => palette.magenta,
+            RuleColor::Cyan => palette.cyan,
+            RuleColor::Orange => palette.orange,
+            RuleColor::Gray => palette.gray,
+            RuleColor::Rgb(r, g, b) => PaletteColor::Rgb((r, g, b)),
+        }
+    }
+}
+
+impl TabRule {
+    pub fn parse_rules(value: &str) -> Vec<TabRule> {
+        value
+            .split(';')
+            .filter_map(|rule| {
+                let mut fields = rule.split(':').map(str::trim);
+                let pattern = fields.next().filter(|p| !p.is_empty())?;
+                let color = fields.next().filter(|c| !c.is_empty());
+                let icon = fields.next().filter(|i| !i.is_empty());
+                let parsed_color = color.and_then(RuleColor::parse);
+                if color.is_some() && parsed_color.is_none() {
+                    eprintln!("Ignoring unknown color in tab rule: {}", rule.trim());
+                }
+                Some(TabRule {
+                    pattern: pattern.to_string(),
+                    color: parsed_color,
+                    icon: icon.map(String::from),
+                })
+            })
+            .collect()
+    }
+
+    pub fn matches(&self, tab_name: &str) -> bool {
+        glob_match(&self.pattern, tab_name)
+    }
+
+    /// The rule applied to `tab_name`, the first matching one wins.
+    pub fn find<'a>(rules: &'a [TabRule], tab_name: &str) -> Option<&'a TabRule> {
+        rules.iter().find(|rule| rule.matches(tab_name))
+    }
+}
+
+// `*` matches any sequence of characters (including none) and `?` any single character
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // position of the last `*` in the pattern and of the text it started matching at
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            // let the last `*` match one more character
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_globs() {
+        // leading, trailing and inner `*`
+        assert!(glob_match("*-ssh", "db-ssh"));
+        assert!(!glob_match("*-ssh", "db-ssh2"));
+        assert!(glob_match("prod*", "prod"));
+        assert!(glob_match("prod*", "production"));
+        assert!(!glob_match("prod*", "preprod"));
+        assert!(glob_match("a*z", "az"));
+        assert!(glob_match("a*z", "abcz"));
+        assert!(!glob_match("a*z", "azb"));
+        assert!(glob_match("*a*b", "xaybab"));
+        // `?` needs exactly one character
+        assert!(glob_match("?", "é"));
+        assert!(!glob_match("?", ""));
+        assert!(!glob_match("??", "a"));
+        assert!(glob_match("*", ""));
+        // without wildcards the whole name has to match
+        assert!(glob_match("prod", "prod"));
+        assert!(!glob_match("prod", "production"));
+        assert!(!glob_match("production", "prod"));
+    }
+
+    #[test]
+    fn ignores_invalid_rules() {
+        let rules = TabRule::parse_rules(" prod* : red : ⚠ ;;:green; db::⛁ ;dev:purple");
+        let parsed: Vec<_> = rules
+            .iter()
+            .map(|rule| (rule.pattern.as_str(), rule.color, rule.icon.as_deref()))
+            .collect();
+        assert_eq!(
+            parsed,
+            [
+                ("prod*", Some(RuleColor::Red), Some("⚠")),
+                ("db", None, Some("⛁")),
+                // unknown colors are dropped, the rest of the rule is kept
+                ("dev", None, None),
+            ]
+        );
+        assert_eq!(
+            RuleColor::parse("#FF8000"),
+            Some(RuleColor::Rgb(255, 128, 0))
+        );
+        assert_eq!(RuleColor::parse("#ff80"), None);
+        assert_eq!(RuleColor::parse("#ff80zz"), None);
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = TabRule::parse_rules("prod-db:blue;prod*:red;*:green");
+        let color = |name| TabRule::find(&rules, name).and_then(|rule| rule.color);
+        assert_eq!(color("prod-db"), Some(RuleColor::Blue));
+        assert_eq!(color("prod-web"), Some(RuleColor::Red));
+        assert_eq!(color("dev"), Some(RuleColor::Green));
+        assert!(TabRule::find(&rules[..1], "dev").is_none());
+    }
+}
@@ -0,0 +1,429 @@
+use std::collections::HashMap;
+
+use zellij_tile::prelude::actions::Action;
+use zellij_tile::prelude::*;
+
+use crate::command::CommandOutput;
+use crate::config::SessionOverview;
+use crate::git::{pane_cwd, GitStatus};
+use crate::pushed::PushedSegment;
+use crate::styled::{style, StyledText};
+use crate::tab::truncate;
+use crate::LinePart;
+
+// max width of pane titles in the pane list
+const PANE_TITLE_WIDTH: usize = 16;
+// max width of session names in the session list
+const SESSION_NAME_WIDTH: usize = 16;
+// max width of git branch names
+const GIT_BRANCH_WIDTH: usize = 24;
+// max width of the text of pushed segments
+const PUSHED_SEGMENT_WIDTH: usize = 40;
+
+// both the tiled and floating layers have a focused pane, the floating one is only the actual
+// focused pane if floating panes are visible
+pub fn is_focused(pane: &PaneInfo, floating_panes_visible: bool) -> bool {
+    pane.is_focused && pane.is_floating == floating_panes_visible
+}
+
+/// Title of the focused pane of the active tab rendered with `format`, where `{title}` is
+/// replaced with the pane title, `{command}` with the command of command panes (falling back to
+/// the first word of the title), `{cwd}` with the working directory (empty when unknown) and
+/// `{id}` with the pane id.
+#[allow(clippy::too_many_arguments)]
+pub fn focused_pane_title(
+    panes: &[PaneInfo],
+    floating_panes_visible: bool,
+    format: &str,
+    max_width: usize,
+    pane_cwds: &HashMap<u32, String>,
+    palette: Palette,
+    background: Option<PaletteColor>,
+) -> Option<LinePart> {
+    let pane = panes
+        .iter()
+        .find(|p| !p.is_suppressed && is_focused(p, floating_panes_visible))?;
+    let command = match &pane.terminal_command {
+        Some(command) => command.as_str(),
+        None => pane.title.split_whitespace().next().unwrap_or_default(),
+    };
+    let text = format
+        .replace("{title}", &pane.title)
+        .replace("{command}", command)
+        .replace("{cwd}", pane_cwd(pane_cwds, pane).unwrap_or_default())
+        .replace("{id}", &pane.id.to_string());
+    if text.trim().is_empty() {
+        return None;
+    }
+    let text = format!(" {} ", truncate(text.trim(), max_width));
+
+    let mut styled_text = StyledText::default();
+    styled_text.push(style(palette.fg, background).bold(), &text);
+    Some(styled_text.into_line_part())
+}
+
+// Order in which keybinding hints are shown, the ones that don't fit are dropped from the end.
+const HINT_ORDER: &[&str] = &[
+    "move",
+    "new",
+    "close",
+    "fullscreen",
+    "floating",
+    "embed",
+    "sync",
+    "rename",
+    "resize",
+    "scroll",
+    "search",
+    "edit",
+    "frames",
+    "pane",
+    "tab",
+    "session",
+    "lock",
+    "unlock",
+    "detach",
+    "quit",
+];
+
+// max number of keys shown for a single hint
+const HINT_MAX_KEYS: usize = 2;
+
+/// What happens when clicking on a `LinePart` that isn't a tab.
+#[derive(Debug, Clone)]
+pub enum ClickAction {
+    FocusPane(PaneId),
+    SwitchSession(String),
+    /// Tab position, starting from 1.
+    SwitchTab(u32),
+}
+
+// `PaneId` doesn't implement `PartialEq`
+impl PartialEq for ClickAction {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                ClickAction::FocusPane(PaneId::Terminal(id)),
+                ClickAction::FocusPane(PaneId::Terminal(other_id)),
+            )
+            | (
+                ClickAction::FocusPane(PaneId::Plugin(id)),
+                ClickAction::FocusPane(PaneId::Plugin(other_id)),
+            ) => id == other_id,
+            (ClickAction::SwitchSession(name), ClickAction::SwitchSession(other_name)) => {
+                name == other_name
+            }
+            (ClickAction::SwitchTab(tab), ClickAction::SwitchTab(other_tab)) => tab == other_tab,
+            _ => false,
+        }
+    }
+}
+
+impl ClickAction {
+    /// Parses the click actions of pushed segments: `tab:<position>`, `pane:<terminal pane id>`
+    /// or `session:<name>`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (kind, target) = value.trim().split_once(':')?;
+        let action = match kind {
+            "tab" => ClickAction::SwitchTab(target.parse().ok().filter(|tab| *tab > 0)?),
+            "pane" => ClickAction::FocusPane(PaneId::Terminal(target.parse().ok()?)),
+            "session" if !target.is_empty() => ClickAction::SwitchSession(target.to_string()),
+            _ => return None,
+        };
+        Some(action)
+    }
+
+    pub fn run(&self) {
+        match self {
+            ClickAction::FocusPane(PaneId::Terminal(id)) => focus_terminal_pane(*id, false),
+            ClickAction::FocusPane(PaneId::Plugin(id)) => focus_plugin_pane(*id, false),
+            ClickAction::SwitchSession(name) => switch_session(Some(name)),
+            ClickAction::SwitchTab(position) => switch_tab_to(*position),
+        }
+    }
+}
+
+/// One entry per pane of the active tab, the focused pane is highlighted and floating, plugin and
+/// exited panes are marked.
+pub fn pane_list(
+    panes: &[PaneInfo],
+    floating_panes_visible: bool,
+    own_plugin_id: u32,
+    palette: Palette,
+    background: Option<PaletteColor>,
+) -> Vec<LinePart> {
+    panes
+        .iter()
+        // skip hidden panes and the bar itself
+        .filter(|p| !(p.is_suppressed || p.is_plugin && p.id == own_plugin_id))
+        .map(|pane| {
+            let mut text = format!(" {}", truncate(&pane.title, PANE_TITLE_WIDTH));
+            if pane.is_floating {
+                text.push_str(" (float)");
+            }
+            if pane.is_plugin {
+                text.push_str(" (plugin)");
+            }
+            if pane.exited {
+                match pane.exit_status {
+                    Some(exit_status) => text.push_str(&format!(" (exited {})", exit_status)),
+                    None => text.push_str(" (exited)"),
+                }
+            }
+            text.push(' ');
+
+            let style = if is_focused(pane, floating_panes_visible) {
+                style(palette.green, background).bold()
+            } else {
+                style(palette.fg, background)
+            };
+            let mut styled_text = StyledText::default();
+            styled_text.push(style, &text);
+            let pane_id = if pane.is_plugin {
+                PaneId::Plugin(pane.id)
+            } else {
+                PaneId::Terminal(pane.id)
+            };
+            LinePart {
+                action: Some(ClickAction::FocusPane(pane_id)),
+                ..styled_text.into_line_part()
+            }
+        })
+        .collect()
+}
+
+/// Current date/time, see `clock::format_time`.
+pub fn clock(text: &str, palette: Palette, background: Option<PaletteColor>) -> LinePart {
+    let mut styled_text = StyledText::default();
+    styled_text.push(style(palette.fg, background).bold(), &format!(" {} ", text));
+    styled_text.into_line_part()
+}
+
+/// Other sessions of the zellij server, either as a count (`3 other sessions +2`) or a list of the live
+/// ones that switches to the clicked session followed by the number of resurrectable ones.
+pub fn session_overview(
+    live_sessions: &[String],
+    resurrectable_sessions: usize,
+    overview: &SessionOverview,
+    palette: Palette,
+    background: Option<PaletteColor>,
+) -> Vec<LinePart> {
+    let resurrectable = (resurrectable_sessions > 0).then(|| {
+        let mut styled_text = StyledText::default();
+        styled_text.push(
+            style(palette.gray, background).italic(),
+            &format!(" +{} ", resurrectable_sessions),
+        );
+        styled_text.into_line_part()
+    });
+
+    match overview {
+        SessionOverview::Hidden => vec![],
+        SessionOverview::Count => {
+            if live_sessions.is_empty() && resurrectable.is_none() {
+                return vec![];
+            }
+            let text = match live_sessions.len() {
+                1 => " 1 other session ".to_string(),
+                count => format!(" {} other sessions ", count),
+            };
+            let mut styled_text = StyledText::default();
+            styled_text.push(style(palette.fg, background), &text);
+            let mut parts = vec![styled_text.into_line_part()];
+            parts.extend(resurrectable);
+            parts
+        }
+        SessionOverview::List => live_sessions
+            .iter()
+            .map(|name| {
+                let mut styled_text = StyledText::default();
+                styled_text.push(
+                    style(palette.fg, background),
+                    &format!(" {} ", truncate(name, SESSION_NAME_WIDTH)),
+                );
+                LinePart {
+                    action: Some(ClickAction::SwitchSession(name.clone())),
+                    ..styled_text.into_line_part()
+                }
+            })
+            .chain(resurrectable)
+            .collect(),
+    }
+}
+
+/// Branch of the focused pane's git repository, followed by `*` when there are uncommitted
+/// changes.
+pub fn git_branch(
+    status: &GitStatus,
+    palette: Palette,
+    background: Option<PaletteColor>,
+) -> LinePart {
+    let mut styled_text = StyledText::default();
+    styled_text
+        .push(
+            style(palette.magenta, background),
+            &format!(" ⎇ {}", truncate(&status.branch, GIT_BRANCH_WIDTH)),
+        )
+        .push(
+            style(palette.orange, background).bold(),
+            if status.dirty { "* " } else { " " },
+        );
+    styled_text.into_line_part()
+}
+
+pub fn pushed_segment(
+    segment: &PushedSegment,
+    palette: Palette,
+    background: Option<PaletteColor>,
+) -> LinePart {
+    let fg_color = segment
+        .color
+        .map_or(palette.fg, |color| color.to_palette_color(palette));
+    let mut styled_text = StyledText::default();
+    styled_text.push(
+        style(fg_color, background),
+        &format!(" {} ", truncate(&segment.text, PUSHED_SEGMENT_WIDTH)),
+    );
+    LinePart {
+        action: segment.click.as_deref().and_then(ClickAction::parse),
+        ..styled_text.into_line_part()
+    }
+}
+
+/// Output of a command segment, failures are reported with the segment id in red.
+pub fn command_segment(
+    id: &str,
+    output: &CommandOutput,
+    palette: Palette,
+    background: Option<PaletteColor>,
+) -> Option<LinePart> {
+    let mut styled_text = StyledText::default();
+    match output {
+        CommandOutput::Pending | CommandOutput::Text { width: 0, .. } => return None,
+        CommandOutput::Text { text, width } => {
+            // the output can change colors on its own
+            let text = style(palette.fg, background).paint(format!(" {} ", text));
+            styled_text.push_styled(&text.to_string(), width + 2);
+        }
+        CommandOutput::Failed(Some(exit_code)) => {
+            styled_text.push(
+                style(palette.red, background),
+                &format!(" {} ✗ {} ", id, exit_code),
+            );
+        }
+        CommandOutput::Failed(None) => {
+            styled_text.push(style(palette.red, background), &format!(" {} ✗ ", id));
+        }
+        CommandOutput::TimedOut => {
+            styled_text.push(
+                style(palette.red, background),
+                &format!(" {} timed out ", id),
+            );
+        }
+    }
+    Some(styled_text.into_line_part())
+}
+
+fn action_hint(action: &Action) -> Option<&'static str> {
+    let hint = match action {
+        Action::GoToPreviousTab
+        | Action::GoToNextTab
+        | Action::MoveFocus(_)
+        | Action::MoveFocusOrTab(_)
+        | Action::MovePane(_) => "move",
+        Action::NewTab(..) | Action::NewPane(..) => "new",
+        Action::CloseTab | Action::CloseFocus => "close",
+        Action::ToggleFocusFullscreen => "fullscreen",
+        Action::ToggleFloatingPanes => "floating",
+        Action::TogglePaneEmbedOrFloating => "embed",
+        Action::ToggleActiveSyncTab => "sync",
+        Action::Resize(..) => "resize",
+        Action::ScrollUp | Action::ScrollDown => "scroll",
+        Action::EditScrollback => "edit",
+        Action::TogglePaneFrames => "frames",
+        Action::Detach => "detach",
+        Action::Quit => "quit",
+        Action::SwitchToMode(mode) => match mode {
+            InputMode::Pane => "pane",
+            InputMode::Tab => "tab",
+            InputMode::Resize => "resize",
+            InputMode::Move => "move",
+            InputMode::Scroll => "scroll",
+            InputMode::EnterSearch => "search",
+            InputMode::Session => "session",
+            InputMode::RenameTab | InputMode::RenamePane => "rename",
+            InputMode::Locked => "lock",
+            // leaving a mode is always possible with ESC/Enter, not worth a hint
+            InputMode::Normal | InputMode::Search | InputMode::Prompt | InputMode::Tmux => {
+                return None
+            }
+        },
+        _ => return None,
+    };
+    Some(hint)
+}
+
+// arrows first as they are the most compact, then plain characters
+fn key_rank(key: &Key) -> usize {
+    match key {
+        Key::Left => 0,
+        Key::Right => 1,
+        Key::Up => 2,
+        Key::Down => 3,
+        Key::Char(_) => 4,
+        _ => 5,
+    }
+}
+
+/// The most useful keybindings of the current mode (e.g. `←→ move`, `n new`, `x close` in tab
+/// mode), one `LinePart` per hint so the ones that don't fit can be dropped.
+pub fn keybind_hints(
+    mode_info: &ModeInfo,
+    palette: Palette,
+    background: Option<PaletteColor>,
+) -> Vec<LinePart> {
+    let mut hints: Vec<(&'static str, Vec<Key>)> = vec![];
+    for (key, actions) in mode_info.get_mode_keybinds() {
+        let hint = match actions.iter().find_map(action_hint) {
+            // the key that unlocks the interface in locked mode switches to normal mode
+            None if mode_info.mode == InputMode::Locked
+                && actions.contains(&Action::SwitchToMode(InputMode::Normal)) =>
+            {
+                "unlock"
+            }
+            Some(hint) => hint,
+            None => continue,
+        };
+        match hints.iter_mut().find(|(h, _)| *h == hint) {
+            Some((_, keys)) => keys.push(key),
+            None => hints.push((hint, vec![key])),
+        }
+    }
+    hints.sort_by_key(|(hint, _)| HINT_ORDER.iter().position(|h| h == hint));
+
+    hints
+        .into_iter()
+        .map(|(hint, mut keys)| {
+            keys.sort_by_key(key_rank);
+            let keys: Vec<String> = keys
+                .iter()
+                .take(HINT_MAX_KEYS)
+                .map(|key| key.to_string())
+                .collect();
+            // single character keys are packed together (e.g. `←→`)
+            let keys = if keys.iter().all(|key| key.chars().count() == 1) {
+                keys.concat()
+            } else {
+                keys.join("/")
+            };
+            let mut styled_text = StyledText::default();
+            styled_text
+                .push(
+                    style(palette.orange, background).bold(),
+                    &format!(" {}", keys),
+                )
+                .push(style(palette.fg, background), &format!(" {} ", hint));
+            styled_text.into_line_part()
+        })
+        .collect()
+}
@@ -0,0 +1,131 @@
+use ansi_term::{Color, Style};
+use unicode_width::UnicodeWidthStr;
+use zellij_tile::prelude::*;
+
+use crate::LinePart;
+
+// Drawn in the color of a block to start it when the bar has no background, the regular
+// separator needs the background color to cut the arrow into the block.
+static TRANSPARENT_LEFT_SEPARATOR: &str = "\u{e0b2}";
+
+/// Background of the bar, `None` in transparent mode so the terminal's default background (and
+/// its translucency) shows through.
+pub fn bar_background(palette: Palette, transparent: bool) -> Option<PaletteColor> {
+    if transparent {
+        return None;
+    }
+    match palette.theme_hue {
+        ThemeHue::Dark => Some(palette.black),
+        ThemeHue::Light => Some(palette.white),
+    }
+}
+
+fn ansi_color(color: PaletteColor) -> Color {
+    match color {
+        PaletteColor::Rgb((r, g, b)) => Color::RGB(r, g, b),
+        PaletteColor::EightBit(color) => Color::Fixed(color),
+    }
+}
+
+/// Same as `style!`, without setting the background when it's `None`.
+pub fn style(foreground: PaletteColor, background: Option<PaletteColor>) -> Style {
+    let style = Style::new().fg(ansi_color(foreground));
+    match background {
+        Some(background) => style.on(ansi_color(background)),
+        None => style,
+    }
+}
+
+/// Concatenates styled spans into a `LinePart`, keeping track of the width of the text on screen
+/// (not its length in bytes) so wide characters like emoji and CJK don't break the alignment of
+/// the bar and click hit-testing.
+#[derive(Debug, Default)]
+pub struct StyledText {
+    part: String,
+    len: usize,
+}
+
+impl StyledText {
+    /// Appends `text` painted with `style`.
+    pub fn push(&mut self, style: Style, text: &str) -> &mut Self {
+        self.part.push_str(&style.paint(text).to_string());
+        self.len += text.width();
+        self
+    }
+
+    /// Appends the separator on the left of a block of `color` drawn over the bar `background`.
+    pub fn push_left_separator(
+        &mut self,
+        separator: &str,
+        color: PaletteColor,
+        background: Option<PaletteColor>,
+    ) -> &mut Self {
+        match background {
+            Some(background) => self.push(style(background, Some(color)), separator),
+            // no arrow fonts
+            None if separator.is_empty() => self,
+            None => self.push(style(color, None), TRANSPARENT_LEFT_SEPARATOR),
+        }
+    }
+
+    /// Appends the separator on the right of a block of `color` drawn over the bar `background`.
+    pub fn push_right_separator(
+        &mut self,
+        separator: &str,
+        color: PaletteColor,
+        background: Option<PaletteColor>,
+    ) -> &mut Self {
+        self.push(style(color, background), separator)
+    }
+
+    /// Appends text that already contains escape sequences, `width` is its width on screen.
+    pub fn push_styled(&mut self, styled_text: &str, width: usize) -> &mut Self {
+        self.part.push_str(styled_text);
+        self.len += width;
+        self
+    }
+
+    pub fn into_line_part(self) -> LinePart {
+        LinePart {
+            part: self.part,
+            len: self.len,
+            tab_index: None,
+            action: None,
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::ansi::{tokenize, Token};
+
+    /// Tab and swap layout names with wide characters.
+    pub const WIDE_NAMES: [&str; 2] = ["日本語のタブ", "🚀 deploy 🔥"];
+
+    /// Width on screen of rendered text, escape sequences aren't printed.
+    pub fn visible_width(rendered: &str) -> usize {
+        tokenize(rendered)
+            .map(|token| match token {
+                Token::Text(text) => text.width(),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn push_counts_width_of_wide_characters() {
+        let palette = Palette::default();
+        for name in WIDE_NAMES {
+            for background in [Some(palette.black), None] {
+                let mut styled_text = StyledText::default();
+                styled_text
+                    .push_left_separator("\u{e0b0}", palette.green, background)
+                    .push(style(palette.fg, Some(palette.green)).bold(), name)
+                    .push_right_separator("\u{e0b0}", palette.green, background);
+                let part = styled_text.into_line_part();
+                assert_eq!(part.len, visible_width(&part.part), "{}", name);
+            }
+        }
+    }
+}
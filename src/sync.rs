@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use zellij_tile::prelude::PipeMessage;
+
+#[derive(Debug)]
+pub enum SyncError {
+    Malformed(serde_json::Error),
+    VersionMismatch { found: u32, expected: u32 },
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::Malformed(err) => write!(f, "malformed payload: {}", err),
+            SyncError::VersionMismatch { found, expected } => {
+                write!(f, "unsupported version {} (expected {})", found, expected)
+            }
+        }
+    }
+}
+
+pub fn to_payload<T: Serialize>(message: &T) -> String {
+    serde_json::to_string(message).unwrap()
+}
+
+/// Deserializes a message sent by another instance, `version` is the version of the message
+/// schema this instance understands.
+pub fn from_payload<T: DeserializeOwned>(payload: &str, version: u32) -> Result<T, SyncError> {
+    // read the version alone first so a newer/older schema is reported as such instead of a
+    // generic deserialization error
+    #[derive(Deserialize)]
+    struct Versioned {
+        version: u32,
+    }
+    let Versioned { version: found } =
+        serde_json::from_str(payload).map_err(SyncError::Malformed)?;
+    if found != version {
+        return Err(SyncError::VersionMismatch {
+            found,
+            expected: version,
+        });
+    }
+    serde_json::from_str(payload).map_err(SyncError::Malformed)
+}
+
+/// Reads the message sent by another instance, logging why it was ignored if it can't be read.
+pub fn read_payload<T: DeserializeOwned>(pipe_message: &PipeMessage, version: u32) -> Option<T> {
+    match pipe_message
+        .payload
+        .as_deref()
+        .map(|payload| from_payload(payload, version))
+    {
+        Some(Ok(message)) => Some(message),
+        Some(Err(err)) => {
+            eprintln!("Ignoring {} message: {}", pipe_message.name, err);
+            None
+        }
+        None => {
+            eprintln!("Ignoring {} message: missing payload", pipe_message.name);
+            None
+        }
+    }
+}
+
+// How long a removed entry rejects older versions of itself broadcasted by other instances. It
+// only needs to outlive the broadcasts other instances sent before they got the removal.
+const TOMBSTONE_TTL_MS: u64 = 60_000;
+
+/// Entry of a [`VersionedMap`].
+pub trait Versioned {
+    /// Milliseconds since the unix epoch.
+    fn updated_at(&self) -> u64;
+
+    /// Whether `self` replaces `other` when merging the state of another instance.
+    fn is_newer_than(&self, other: &Self) -> bool {
+        self.updated_at() > other.updated_at()
+    }
+}
+
+/// Entries shared between instances plus tombstones of the ones removed recently, so stale
+/// entries broadcasted by other instances aren't brought back.
+#[derive(Debug)]
+pub struct VersionedMap<K, V> {
+    entries: BTreeMap<K, V>,
+    // key -> removed at (milliseconds since the unix epoch)
+    tombstones: BTreeMap<K, u64>,
+}
+
+impl<K, V> Default for VersionedMap<K, V> {
+    fn default() -> Self {
+        VersionedMap {
+            entries: BTreeMap::new(),
+            tombstones: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Versioned + Clone> VersionedMap<K, V> {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key)
+    }
+
+    pub fn entries(&self) -> &BTreeMap<K, V> {
+        &self.entries
+    }
+
+    pub fn tombstones(&self) -> &BTreeMap<K, u64> {
+        &self.tombstones
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.entries.values_mut()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        self.entries.insert(key, value);
+    }
+
+    /// Removes an entry and records a tombstone for it.
+    pub fn remove(&mut self, key: &K, removed_at: u64) -> Option<V> {
+        self.add_tombstone(key.clone(), removed_at);
+        self.entries.remove(key)
+    }
+
+    /// Keeps the entries `f` returns `true` for, without tombstones (e.g. expired entries).
+    pub fn retain(&mut self, f: impl FnMut(&K, &mut V) -> bool) {
+        self.entries.retain(f);
+    }
+
+    /// Merges the state broadcasted by another instance: entries are kept if they're newer than
+    /// the current ones and not older than their tombstone, and tombstones remove the entries
+    /// that are older.
+    ///
+    /// Returns `true` if any entry changed.
+    pub fn apply_sync(
+        &mut self,
+        entries: impl IntoIterator<Item = (K, V)>,
+        tombstones: impl IntoIterator<Item = (K, u64)>,
+    ) -> bool {
+        let mut changed = false;
+        for (key, removed_at) in tombstones {
+            changed |= self.apply_tombstone(key, removed_at);
+        }
+        for (key, value) in entries {
+            let is_tombstoned = self
+                .tombstones
+                .get(&key)
+                .is_some_and(|removed_at| value.updated_at() <= *removed_at);
+            let is_newer = match self.entries.get(&key) {
+                Some(current) => value.is_newer_than(current),
+                None => true,
+            };
+            if !is_tombstoned && is_newer {
+                self.entries.insert(key, value);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Applies a removal made by another instance.
+    ///
+    /// Returns `true` if an entry was removed.
+    pub fn apply_tombstone(&mut self, key: K, removed_at: u64) -> bool {
+        let is_older = self
+            .entries
+            .get(&key)
+            .is_some_and(|value| value.updated_at() <= removed_at);
+        if is_older {
+            self.entries.remove(&key);
+        }
+        self.add_tombstone(key, removed_at);
+        is_older
+    }
+
+    pub fn prune_tombstones(&mut self, now: u64) {
+        self.tombstones
+            .retain(|_, removed_at| now.saturating_sub(*removed_at) < TOMBSTONE_TTL_MS);
+    }
+
+    fn add_tombstone(&mut self, key: K, removed_at: u64) {
+        let tombstone = self.tombstones.entry(key).or_default();
+        *tombstone = (*tombstone).max(removed_at);
+    }
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
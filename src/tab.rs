@@ -1,107 +1,344 @@
+use std::collections::HashMap;
+
+use crate::rule::TabRule;
+use crate::styled::{style, StyledText};
 use crate::{line::tab_separator, LinePart};
-use ansi_term::{ANSIString, ANSIStrings};
-use unicode_width::UnicodeWidthStr;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 use zellij_tile::prelude::*;
-use zellij_tile_utils::style;
 
-fn cursors(focused_clients: &[ClientId], palette: Palette) -> (Vec<ANSIString>, usize) {
-    // cursor section, text length
-    let mut len = 0;
-    let mut cursors = vec![];
-    for client_id in focused_clients.iter() {
-        if let Some(color) = client_id_to_colors(*client_id, palette) {
-            cursors.push(style!(color.1, color.0).paint(" "));
-            len += 1;
+static ACTIVITY_MARKER: &str = "•";
+static TILED_PANES_ICON: &str = "▣";
+static FLOATING_PANES_VISIBLE_ICON: &str = "▲";
+static FLOATING_PANES_HIDDEN_ICON: &str = "△";
+
+// max width of tab names when compressed with `TabCompression::Truncated`
+const TRUNCATED_NAME_WIDTH: usize = 8;
+
+/// How much tab labels are shrunk to fit more tabs in the line, from least to most compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TabCompression {
+    Full,
+    Truncated,
+    IndexAndIcons,
+    Index,
+}
+
+impl TabCompression {
+    pub const ALL: [TabCompression; 4] = [
+        TabCompression::Full,
+        TabCompression::Truncated,
+        TabCompression::IndexAndIcons,
+        TabCompression::Index,
+    ];
+}
+
+/// Per-tab state raised in the background (alerts and activity/silence monitoring).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TabIndicators {
+    /// The tab has an alert, blinking between the alert color (when `alternate_color` is set)
+    /// and the regular one.
+    pub alert: bool,
+    pub alternate_color: bool,
+    pub success: bool,
+    pub activity: bool,
+    pub silence: bool,
+    /// Elapsed time of the running job or duration of the finished one, in milliseconds.
+    pub duration: Option<u64>,
+    pub pane_counts: Option<PaneCounts>,
+}
+
+/// Glyphs marking tabs with a fullscreen pane or synced panes, empty ones aren't rendered.
+#[derive(Debug, Clone, Copy)]
+pub struct TabIcons<'a> {
+    pub fullscreen: &'a str,
+    pub sync: &'a str,
+}
+
+/// Number of terminal panes in a tab, plugin panes (like the bar itself) aren't counted.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PaneCounts {
+    pub tiled: usize,
+    pub floating: usize,
+}
+
+impl PaneCounts {
+    pub fn new(panes: &[PaneInfo]) -> Self {
+        let mut counts = PaneCounts::default();
+        for pane in panes.iter().filter(|p| !p.is_plugin && !p.is_suppressed) {
+            if pane.is_floating {
+                counts.floating += 1;
+            } else {
+                counts.tiled += 1;
+            }
+        }
+        counts
+    }
+}
+
+/// Formats milliseconds as a compact duration, e.g. `42s`, `3m05s` or `1h02m`.
+pub fn format_duration(millis: u64) -> String {
+    let secs = millis / 1000;
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h{:02}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
+pub fn truncate(name: &str, max_width: usize) -> String {
+    if name.width() <= max_width {
+        return name.to_string();
+    }
+    let mut truncated = String::new();
+    let mut width = 0;
+    for c in name.chars() {
+        let char_width = c.width().unwrap_or(0);
+        // leave room for the ellipsis
+        if width + char_width + 1 > max_width {
+            break;
         }
+        truncated.push(c);
+        width += char_width;
     }
-    (cursors, len)
+    truncated.push('…');
+    truncated
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn render_tab(
-    text: String,
+    mut text: String,
     tab: &TabInfo,
     palette: Palette,
+    background: Option<PaletteColor>,
     separator: &str,
-    alternate_color: bool,
-    success: bool,
+    indicators: TabIndicators,
+    compression: TabCompression,
+    icons: TabIcons,
+    rule: Option<&TabRule>,
 ) -> LinePart {
+    if compression <= TabCompression::IndexAndIcons {
+        if tab.is_fullscreen_active && !icons.fullscreen.is_empty() {
+            text.push(' ');
+            text.push_str(icons.fullscreen);
+        }
+        if tab.is_sync_panes_active && !icons.sync.is_empty() {
+            text.push(' ');
+            text.push_str(icons.sync);
+        }
+    }
+    if let Some(duration) = indicators.duration {
+        if compression <= TabCompression::Truncated {
+            text.push_str(&format!(" ({})", format_duration(duration)));
+        }
+    }
+    if let Some(pane_counts) = indicators.pane_counts {
+        if compression <= TabCompression::IndexAndIcons {
+            text.push_str(&format!(" {}{}", TILED_PANES_ICON, pane_counts.tiled));
+            if pane_counts.floating > 0 {
+                let floating_icon = if tab.are_floating_panes_visible {
+                    FLOATING_PANES_VISIBLE_ICON
+                } else {
+                    FLOATING_PANES_HIDDEN_ICON
+                };
+                text.push_str(&format!(" {}{}", floating_icon, pane_counts.floating));
+            }
+        }
+    }
+    if indicators.activity && compression <= TabCompression::IndexAndIcons {
+        text.push(' ');
+        text.push_str(ACTIVITY_MARKER);
+    }
     let focused_clients = tab.other_focused_clients.as_slice();
-    let separator_width = separator.width();
+    // Alerts are cleared when focusing a tab so they never apply to the active one, fullscreen on
+    // inactive tabs is only shown through its icon to keep the alert colors unambiguous.
+    // Colors from tab rules replace the base color of inactive tabs but not the alert/silence
+    // ones, the active tab keeps its color and gets the rule color on its separators instead so
+    // it still stands out.
+    let rule_color = rule
+        .and_then(|rule| rule.color)
+        .map(|color| color.to_palette_color(palette));
     let background_color = if tab.active {
         if tab.is_fullscreen_active {
             palette.orange
         } else {
             palette.green
         }
-    } else if alternate_color {
-        if success {
+    } else if indicators.alert && indicators.alternate_color {
+        if indicators.success {
             palette.green
         } else {
             palette.red
         }
-    } else {
+    } else if indicators.silence {
+        palette.yellow
+    } else if indicators.alert {
+        // the rule color could be the same as the alert one and hide the blinking
         palette.fg
+    } else {
+        rule_color.unwrap_or(palette.fg)
+    };
+    let separator_color = match rule_color {
+        Some(rule_color) if tab.active => rule_color,
+        _ => background_color,
     };
     let foreground_color = match palette.theme_hue {
         ThemeHue::Dark => palette.black,
         ThemeHue::Light => palette.white,
     };
-    let left_separator = style!(foreground_color, background_color).paint(separator);
-    let mut tab_text_len = text.width() + (separator_width * 2) + 2; // + 2 for padding
-
-    let tab_styled_text = (if !tab.active {
-        style!(foreground_color, background_color).italic().bold()
-    } else {
-        style!(foreground_color, background_color).bold()
-    })
-    .paint(format!(" {} ", text));
-
-    let right_separator = style!(background_color, foreground_color).paint(separator);
-    let tab_styled_text = if !focused_clients.is_empty() {
-        let (cursor_section, extra_length) = cursors(focused_clients, palette);
-        tab_text_len += extra_length;
-        let mut s = String::new();
-        let cursor_beginning = style!(foreground_color, background_color)
+    let text_style = if !tab.active {
+        style(foreground_color, Some(background_color))
+            .italic()
             .bold()
-            .paint("[")
-            .to_string();
-        let cursor_section = ANSIStrings(&cursor_section).to_string();
-        let cursor_end = style!(foreground_color, background_color)
-            .bold()
-            .paint("]")
-            .to_string();
-        s.push_str(&left_separator.to_string());
-        s.push_str(&tab_styled_text.to_string());
-        s.push_str(&cursor_beginning);
-        s.push_str(&cursor_section);
-        s.push_str(&cursor_end);
-        s.push_str(&right_separator.to_string());
-        s
     } else {
-        ANSIStrings(&[left_separator, tab_styled_text, right_separator]).to_string()
+        style(foreground_color, Some(background_color)).bold()
     };
 
+    let mut tab_styled_text = StyledText::default();
+    tab_styled_text
+        .push_left_separator(separator, separator_color, background)
+        .push(text_style, &format!(" {} ", text));
+    if !focused_clients.is_empty() {
+        // one colored cell per client focused on this tab
+        let cursor_style = style(foreground_color, Some(background_color)).bold();
+        tab_styled_text.push(cursor_style, "[");
+        for client_id in focused_clients {
+            if let Some(color) = client_id_to_colors(*client_id, palette) {
+                tab_styled_text.push(style(color.1, Some(color.0)), " ");
+            }
+        }
+        tab_styled_text.push(cursor_style, "]");
+    }
+    tab_styled_text.push_right_separator(separator, separator_color, background);
+
     LinePart {
-        part: tab_styled_text,
-        len: tab_text_len,
         tab_index: Some(tab.position),
+        ..tab_styled_text.into_line_part()
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn tab_style(
-    mut tabname: String,
+    tabname: String,
     tab: &TabInfo,
     palette: Palette,
+    background: Option<PaletteColor>,
     capabilities: PluginCapabilities,
-    alternate_color: bool,
-    success: bool,
+    indicators: TabIndicators,
+    compression: TabCompression,
+    icons: TabIcons,
+    rule: Option<&TabRule>,
 ) -> LinePart {
     let separator = tab_separator(capabilities);
-    if tab.is_sync_panes_active {
-        tabname.push_str(" (Sync)");
+    // tabs are indexed starting from 1 so we need to add 1
+    let index = tab.position + 1;
+    let mut tabname = match compression {
+        TabCompression::Full => format!("{} {}", index, tabname),
+        TabCompression::Truncated => {
+            format!("{} {}", index, truncate(&tabname, TRUNCATED_NAME_WIDTH))
+        }
+        TabCompression::IndexAndIcons | TabCompression::Index => index.to_string(),
+    };
+    if let Some(icon) = rule.and_then(|rule| rule.icon.as_deref()) {
+        if compression <= TabCompression::IndexAndIcons {
+            // icon goes right after the index
+            tabname.insert_str(index.to_string().len(), &format!(" {}", icon));
+        }
+    }
+
+    render_tab(
+        tabname,
+        tab,
+        palette,
+        background,
+        separator,
+        indicators,
+        compression,
+        icons,
+        rule,
+    )
+}
+
+/// Tabs rendered by the last renders, by position and compression. A tab is only rendered again
+/// when its state changes, so blinking alerts don't re-render every tab on each tick.
+///
+/// Icons and rules come from the configuration, which doesn't change, and the rule of a tab
+/// depends on its name, which is part of its `TabInfo`.
+#[derive(Debug, Default)]
+pub struct TabCache {
+    // inputs shared by all the tabs, everything is rendered again when they change
+    style: Option<(Palette, Option<PaletteColor>, PluginCapabilities)>,
+    tabs: HashMap<(usize, TabCompression), CachedTab>,
+}
+
+#[derive(Debug)]
+struct CachedTab {
+    tabname: String,
+    tab: TabInfo,
+    indicators: TabIndicators,
+    part: LinePart,
+}
+
+impl TabCache {
+    /// Drops everything if the colors or capabilities changed, and the tabs past `tab_count`.
+    pub fn prune(
+        &mut self,
+        tab_count: usize,
+        palette: Palette,
+        background: Option<PaletteColor>,
+        capabilities: PluginCapabilities,
+    ) {
+        let style = Some((palette, background, capabilities));
+        if self.style != style {
+            self.style = style;
+            self.tabs.clear();
+        }
+        self.tabs.retain(|(position, _), _| *position < tab_count);
     }
 
-    render_tab(tabname, tab, palette, separator, alternate_color, success)
+    /// Same as [`tab_style`], reusing the tab rendered last time if nothing changed since.
+    #[allow(clippy::too_many_arguments)]
+    pub fn tab_style(
+        &mut self,
+        tabname: &str,
+        tab: &TabInfo,
+        palette: Palette,
+        background: Option<PaletteColor>,
+        capabilities: PluginCapabilities,
+        indicators: TabIndicators,
+        compression: TabCompression,
+        icons: TabIcons,
+        rule: Option<&TabRule>,
+    ) -> LinePart {
+        let key = (tab.position, compression);
+        if let Some(cached) = self.tabs.get(&key) {
+            if cached.tabname == tabname && cached.tab == *tab && cached.indicators == indicators {
+                return cached.part.clone();
+            }
+        }
+        let part = tab_style(
+            tabname.to_string(),
+            tab,
+            palette,
+            background,
+            capabilities,
+            indicators,
+            compression,
+            icons,
+            rule,
+        );
+        self.tabs.insert(
+            key,
+            CachedTab {
+                tabname: tabname.to_string(),
+                tab: tab.clone(),
+                indicators,
+                part: part.clone(),
+            },
+        );
+        part
+    }
 }
 
 pub(crate) fn get_tab_to_focus(
@@ -132,3 +369,52 @@ pub(crate) fn get_clicked_line_part(
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::styled::tests::{visible_width, WIDE_NAMES};
+
+    #[test]
+    fn tab_len_is_width_of_wide_names() {
+        let palette = Palette::default();
+        let icons = TabIcons {
+            fullscreen: "⛶",
+            sync: "⇄",
+        };
+        let indicators = TabIndicators {
+            activity: true,
+            duration: Some(90_000),
+            pane_counts: Some(PaneCounts {
+                tiled: 2,
+                floating: 1,
+            }),
+            ..Default::default()
+        };
+        for name in WIDE_NAMES {
+            let tab = TabInfo {
+                name: name.to_string(),
+                active: true,
+                is_sync_panes_active: true,
+                other_focused_clients: vec![2],
+                ..Default::default()
+            };
+            for compression in TabCompression::ALL {
+                for background in [Some(palette.black), None] {
+                    let part = tab_style(
+                        name.to_string(),
+                        &tab,
+                        palette,
+                        background,
+                        PluginCapabilities::default(),
+                        indicators,
+                        compression,
+                        icons,
+                        None,
+                    );
+                    assert_eq!(part.len, visible_width(&part.part), "{}", name);
+                }
+            }
+        }
+    }
+}